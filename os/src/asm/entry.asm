@@ -1,12 +1,23 @@
     .section .text.entry
     .globl _start
 _start:
+    # Every hart starts here. Harts beyond config::CPUS are parked, the others get
+    # boot_stack_top - hartid * BOOT_STACK_SIZE as their stack.
+    csrr t0, mhartid
+    li t1, {CPUS}
+    bgeu t0, t1, park
+    li t1, {BOOT_STACK_SIZE}
+    mul t0, t0, t1
     la sp, boot_stack_top
+    sub sp, sp, t0
     call sbi_entry
+park:
+    wfi
+    j park
 
     .section .bss.stack
     .globl boot_stack_low
 boot_stack_low:
-    .space 4096 * 16
+    .space {BOOT_STACK_SIZE} * {CPUS}
     .globl boot_stack_top
-boot_stack_top:
\ No newline at end of file
+boot_stack_top:
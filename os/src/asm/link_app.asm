@@ -3,7 +3,7 @@
     .section .data
     .global _num_app
 _num_app:
-    .quad 17
+    .quad 36
     .quad app_0_start
     .quad app_1_start
     .quad app_2_start
@@ -21,10 +21,32 @@ _num_app:
     .quad app_14_start
     .quad app_15_start
     .quad app_16_start
-    .quad app_16_end
+    .quad app_17_start
+    .quad app_18_start
+    .quad app_19_start
+    .quad app_20_start
+    .quad app_21_start
+    .quad app_22_start
+    .quad app_23_start
+    .quad app_24_start
+    .quad app_25_start
+    .quad app_26_start
+    .quad app_27_start
+    .quad app_28_start
+    .quad app_29_start
+    .quad app_30_start
+    .quad app_31_start
+    .quad app_32_start
+    .quad app_33_start
+    .quad app_34_start
+    .quad app_35_start
+    .quad app_35_end
 
     .global _app_names
 _app_names:
+    .string "cap_test"
+    .string "echo_client"
+    .string "echo_server"
     .string "exit"
     .string "fantastic_text"
     .string "forkexec"
@@ -32,12 +54,28 @@ _app_names:
     .string "forktest2"
     .string "forktest_simple"
     .string "forktree"
+    .string "futex_test"
+    .string "heap_test"
     .string "hello_world"
     .string "initproc"
     .string "matrix"
+    .string "mmap_test"
+    .string "name_server"
+    .string "name_service_test"
+    .string "notification_test"
+    .string "philosophers"
+    .string "priority_test"
+    .string "producer_consumer"
+    .string "shm_test"
+    .string "signal_test"
     .string "sleep"
     .string "sleep_simple"
+    .string "smp_test"
     .string "stack_overflow"
+    .string "threads"
+    .string "tlb_test"
+    .string "uart_driver"
+    .string "usage_test"
     .string "user_shell"
     .string "usertests"
     .string "usertests-simple"
@@ -48,7 +86,7 @@ _app_names:
     .global app_0_end
     .align 3
 app_0_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/exit"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/cap_test"
 app_0_end:
 
     .section .data
@@ -56,7 +94,7 @@ app_0_end:
     .global app_1_end
     .align 3
 app_1_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/fantastic_text"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/echo_client"
 app_1_end:
 
     .section .data
@@ -64,7 +102,7 @@ app_1_end:
     .global app_2_end
     .align 3
 app_2_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forkexec"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/echo_server"
 app_2_end:
 
     .section .data
@@ -72,7 +110,7 @@ app_2_end:
     .global app_3_end
     .align 3
 app_3_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktest"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/exit"
 app_3_end:
 
     .section .data
@@ -80,7 +118,7 @@ app_3_end:
     .global app_4_end
     .align 3
 app_4_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktest2"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/fantastic_text"
 app_4_end:
 
     .section .data
@@ -88,7 +126,7 @@ app_4_end:
     .global app_5_end
     .align 3
 app_5_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktest_simple"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forkexec"
 app_5_end:
 
     .section .data
@@ -96,7 +134,7 @@ app_5_end:
     .global app_6_end
     .align 3
 app_6_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktree"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktest"
 app_6_end:
 
     .section .data
@@ -104,7 +142,7 @@ app_6_end:
     .global app_7_end
     .align 3
 app_7_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/hello_world"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktest2"
 app_7_end:
 
     .section .data
@@ -112,7 +150,7 @@ app_7_end:
     .global app_8_end
     .align 3
 app_8_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/initproc"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktest_simple"
 app_8_end:
 
     .section .data
@@ -120,7 +158,7 @@ app_8_end:
     .global app_9_end
     .align 3
 app_9_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/matrix"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/forktree"
 app_9_end:
 
     .section .data
@@ -128,7 +166,7 @@ app_9_end:
     .global app_10_end
     .align 3
 app_10_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/sleep"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/futex_test"
 app_10_end:
 
     .section .data
@@ -136,7 +174,7 @@ app_10_end:
     .global app_11_end
     .align 3
 app_11_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/sleep_simple"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/heap_test"
 app_11_end:
 
     .section .data
@@ -144,7 +182,7 @@ app_11_end:
     .global app_12_end
     .align 3
 app_12_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/stack_overflow"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/hello_world"
 app_12_end:
 
     .section .data
@@ -152,7 +190,7 @@ app_12_end:
     .global app_13_end
     .align 3
 app_13_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/user_shell"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/initproc"
 app_13_end:
 
     .section .data
@@ -160,7 +198,7 @@ app_13_end:
     .global app_14_end
     .align 3
 app_14_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/usertests"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/matrix"
 app_14_end:
 
     .section .data
@@ -168,7 +206,7 @@ app_14_end:
     .global app_15_end
     .align 3
 app_15_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/usertests-simple"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/mmap_test"
 app_15_end:
 
     .section .data
@@ -176,5 +214,157 @@ app_15_end:
     .global app_16_end
     .align 3
 app_16_start:
-    .incbin "../user/target/riscv64gc-unknown-none-elf/release/yield"
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/name_server"
 app_16_end:
+
+    .section .data
+    .global app_17_start
+    .global app_17_end
+    .align 3
+app_17_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/name_service_test"
+app_17_end:
+
+    .section .data
+    .global app_18_start
+    .global app_18_end
+    .align 3
+app_18_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/notification_test"
+app_18_end:
+
+    .section .data
+    .global app_19_start
+    .global app_19_end
+    .align 3
+app_19_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/philosophers"
+app_19_end:
+
+    .section .data
+    .global app_20_start
+    .global app_20_end
+    .align 3
+app_20_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/priority_test"
+app_20_end:
+
+    .section .data
+    .global app_21_start
+    .global app_21_end
+    .align 3
+app_21_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/producer_consumer"
+app_21_end:
+
+    .section .data
+    .global app_22_start
+    .global app_22_end
+    .align 3
+app_22_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/shm_test"
+app_22_end:
+
+    .section .data
+    .global app_23_start
+    .global app_23_end
+    .align 3
+app_23_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/signal_test"
+app_23_end:
+
+    .section .data
+    .global app_24_start
+    .global app_24_end
+    .align 3
+app_24_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/sleep"
+app_24_end:
+
+    .section .data
+    .global app_25_start
+    .global app_25_end
+    .align 3
+app_25_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/sleep_simple"
+app_25_end:
+
+    .section .data
+    .global app_26_start
+    .global app_26_end
+    .align 3
+app_26_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/smp_test"
+app_26_end:
+
+    .section .data
+    .global app_27_start
+    .global app_27_end
+    .align 3
+app_27_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/stack_overflow"
+app_27_end:
+
+    .section .data
+    .global app_28_start
+    .global app_28_end
+    .align 3
+app_28_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/threads"
+app_28_end:
+
+    .section .data
+    .global app_29_start
+    .global app_29_end
+    .align 3
+app_29_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/tlb_test"
+app_29_end:
+
+    .section .data
+    .global app_30_start
+    .global app_30_end
+    .align 3
+app_30_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/uart_driver"
+app_30_end:
+
+    .section .data
+    .global app_31_start
+    .global app_31_end
+    .align 3
+app_31_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/usage_test"
+app_31_end:
+
+    .section .data
+    .global app_32_start
+    .global app_32_end
+    .align 3
+app_32_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/user_shell"
+app_32_end:
+
+    .section .data
+    .global app_33_start
+    .global app_33_end
+    .align 3
+app_33_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/usertests"
+app_33_end:
+
+    .section .data
+    .global app_34_start
+    .global app_34_end
+    .align 3
+app_34_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/usertests-simple"
+app_34_end:
+
+    .section .data
+    .global app_35_start
+    .global app_35_end
+    .align 3
+app_35_start:
+    .incbin "../user/target/riscv64gc-unknown-none-elf/release/yield"
+app_35_end:
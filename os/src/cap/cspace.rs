@@ -0,0 +1,58 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::cap::{Capability, CSPACE_FIRST_FREE};
+use crate::config::CSPACE_SLOTS;
+
+/// The capability space of a process: a table of slots, each holding at most one capability.
+/// User space names kernel objects only through slot indexes.
+pub struct CSpace {
+    slots: Vec<Option<Capability>>
+}
+
+impl CSpace {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![None; CSPACE_SLOTS]
+        }
+    }
+    /// The capability in `slot`, if there is one that has not been revoked.
+    pub fn get(&self, slot: usize) -> Option<&Capability> {
+        self.slots.get(slot)?.as_ref().filter(|cap| cap.is_valid())
+    }
+    pub fn is_free(&self, slot: usize) -> bool {
+        slot < self.slots.len() && self.get(slot).is_none()
+    }
+    /// Puts `cap` into the lowest free slot that is not reserved and returns the slot.
+    pub fn insert(&mut self, cap: Capability) -> Option<usize> {
+        let slot = (CSPACE_FIRST_FREE..self.slots.len()).find(|&slot| self.is_free(slot))?;
+        self.slots[slot] = Some(cap);
+        Some(slot)
+    }
+    /// Puts `cap` into `slot`. Returns false if the slot is taken or out of range.
+    pub fn insert_at(&mut self, slot: usize, cap: Capability) -> bool {
+        if !self.is_free(slot) {
+            return false;
+        }
+        self.slots[slot] = Some(cap);
+        true
+    }
+    pub fn remove(&mut self, slot: usize) -> Option<Capability> {
+        let cap = self.slots.get_mut(slot)?.take()?;
+        cap.is_valid().then_some(cap)
+    }
+    /// A copy for a forked child, where every capability is derived from the parent's so
+    /// that revoking the parent's also takes the child's away.
+    pub fn derive_all(&self) -> Self {
+        Self {
+            slots: (0..self.slots.len())
+                .map(|slot| self.get(slot).map(|cap| cap.derive(cap.rights, cap.badge)))
+                .collect()
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &Capability> {
+        (0..self.slots.len()).filter_map(|slot| self.get(slot))
+    }
+    pub fn clear(&mut self) {
+        self.slots.iter_mut().for_each(|slot| *slot = None);
+    }
+}
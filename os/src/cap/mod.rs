@@ -0,0 +1,115 @@
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use crate::ipc::endpoint::Endpoint;
+use crate::ipc::notification::Notification;
+use crate::mem::frame_allocator::FrameTracker;
+use crate::mem::shared_memory::SharedMemory;
+use crate::sync::spin::SpinLock;
+use crate::task::process::ProcessControlBlock;
+
+pub mod cspace;
+
+/// Well-known slots every process starts with.
+pub const CAP_ADDRESS_SPACE: usize = 0;
+pub const CAP_IRQ_CONTROL: usize = 1;
+/// An endpoint served by the name server, see `ProcessControlBlock::new`.
+pub const CAP_NAME_SERVER: usize = 2;
+/// Slots below this one are reserved for well-known capabilities.
+pub const CSPACE_FIRST_FREE: usize = 4;
+
+bitflags! {
+    #[derive(Copy, Clone, PartialEq)]
+    pub struct CapRights: usize {
+        /// Receive on an endpoint, wait on a notification, map memory readable.
+        const READ = 1 << 0;
+        /// Send or call on an endpoint, signal a notification, map memory writable, change an
+        /// address space.
+        const WRITE = 1 << 1;
+    }
+}
+
+/// Kernel objects a capability can refer to.
+#[derive(Clone)]
+pub enum CapObject {
+    Endpoint(Arc<Endpoint>),
+    Notification(Arc<Notification>),
+    Frame(Arc<FrameTracker>),
+    SharedMemory(Arc<SharedMemory>),
+    /// The address space of a process, which may be replaced by exec.
+    AddressSpace(Weak<ProcessControlBlock>),
+    /// Hands out handlers for interrupt sources.
+    IrqControl,
+    IrqHandler(usize)
+}
+
+/// Node of the derivation tree. Revoking a capability invalidates the nodes of every
+/// capability that was copied or minted from it, directly or not.
+struct CapNode {
+    valid: SpinLock<bool>,
+    children: SpinLock<Vec<Arc<CapNode>>>
+}
+
+impl CapNode {
+    fn new() -> Arc<Self> {
+        Arc::new(Self {
+            valid: SpinLock::new(true),
+            children: SpinLock::new(Vec::new())
+        })
+    }
+    fn is_valid(&self) -> bool {
+        *self.valid.exclusive_access()
+    }
+    fn invalidate_children(&self) {
+        let children: Vec<_> = self.children.exclusive_access().drain(..).collect();
+        for child in children {
+            *child.valid.exclusive_access() = false;
+            child.invalidate_children();
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Capability {
+    pub object: CapObject,
+    pub rights: CapRights,
+    /// Identifies the holder to the receiver of messages sent through an endpoint, or the
+    /// bits ORed into a notification on every signal.
+    pub badge: usize,
+    node: Arc<CapNode>
+}
+
+impl Capability {
+    /// A capability with all rights, the root of a new derivation tree.
+    pub fn new(object: CapObject) -> Self {
+        Self {
+            object,
+            rights: CapRights::all(),
+            badge: 0,
+            node: CapNode::new()
+        }
+    }
+    pub fn is_valid(&self) -> bool {
+        self.node.is_valid()
+    }
+    /// Creates a child capability with at most this one's rights.
+    pub fn derive(&self, rights: CapRights, badge: usize) -> Self {
+        let node = CapNode::new();
+        let mut children = self.node.children.exclusive_access();
+        // Forget children that were deleted and have no children of their own.
+        children.retain(|child| {
+            Arc::strong_count(child) > 1 || !child.children.exclusive_access().is_empty()
+        });
+        children.push(node.clone());
+        Self {
+            object: self.object.clone(),
+            rights: self.rights & rights,
+            badge,
+            node
+        }
+    }
+    /// Invalidates every capability derived from this one. The capability itself stays.
+    pub fn revoke(&self) {
+        self.node.invalidate_children();
+    }
+}
@@ -3,19 +3,43 @@ use crate::mem::address::PAGE_SIZE;
 // Ports and addresses of MMIO devices.
 pub const UART_BASE_ADDR: usize = 0x1000_0000;
 pub const SYSTEM_RESET_BASE_ADDR: usize = 0x10_0000;
+pub const CLINT_BASE_ADDR: usize = 0x0200_0000;
+pub const CLINT_SIZE: usize = 0x1_0000;
+/// Software interrupt pending bits of the harts, one 32-bit register each.
+pub const MSIP_BASE_ADDR: usize = CLINT_BASE_ADDR;
 pub const TIMER_ADDR: usize = 0x0200_bff8;
 pub const TIMER_CMP_BASE_ADDR: usize = 0x0200_4000;
+pub const PLIC_BASE_ADDR: usize = 0x0c00_0000;
+pub const PLIC_SIZE: usize = 0x40_0000;
+
+/// Device registers user-space drivers may map, as (base, size).
+pub const USER_MMIO: &[(usize, usize)] = &[
+    (UART_BASE_ADDR, 0x1000),
+];
+
+// Interrupt sources of the PLIC.
+pub const UART_IRQ: usize = 10;
 
 // Memory layout
 pub const KERNEL_BASE_ADDR: usize = 0x8000_0000;
 pub const APP_BASE_ADDR: usize = 0x8040_0000;
 pub const APP_SIZE_LIMIT: usize = 0x2_0000;
+/// Initial size of the user stack if the ELF file does not ask for one.
 pub const USER_STACK_SIZE: usize = 4096 * 2;
+/// The user stack grows downwards on page faults up to this size.
+pub const USER_STACK_LIMIT: usize = 4096 * 64;
+/// Stack size of every thread but the first, which cannot grow.
+pub const THREAD_STACK_SIZE: usize = 4096 * 16;
 pub const KERNEL_STACK_SIZE: usize = 4096 * 2;
 pub const KERNEL_HEAP_SIZE : usize = 0x30_0000;
 pub const MEMORY_END : usize = 0x8080_0000;
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
+pub const MMAP_BASE: usize = 0x10_0000_0000;
+pub const USER_SPACE_END: usize = 0x40_0000_0000;
+pub const USER_STACK_TOP: usize = USER_SPACE_END;
+/// Mappings stay below the stack's reserved range and the guard page under it.
+pub const MMAP_END: usize = USER_STACK_TOP - USER_STACK_LIMIT - PAGE_SIZE;
 
 pub fn kernel_stack_position(id: usize) -> (usize, usize) {
     let top = TRAMPOLINE - id * (KERNEL_STACK_SIZE + PAGE_SIZE);
@@ -23,9 +47,19 @@ pub fn kernel_stack_position(id: usize) -> (usize, usize) {
     (bottom, top)
 }
 
+/// Every thread has its own trap context page, the one of thread `tid` is `tid` pages below
+/// `TRAP_CONTEXT`.
+pub fn trap_context_position(tid: usize) -> usize {
+    TRAP_CONTEXT - tid * PAGE_SIZE
+}
+
 // Environment config
 pub const MAX_APP_NUM: usize = 16;
-pub const CPUS: usize = 1;
+pub const CSPACE_SLOTS: usize = 64;
+/// Harts brought up at boot, `entry.asm` parks any others.
+pub const CPUS: usize = 4;
+/// Stack each hart boots on and keeps as its idle stack, laid out by `entry.asm`.
+pub const BOOT_STACK_SIZE: usize = 4096 * 16;
 
 // Timer config
 pub const TIMER_FREQ: usize = 12_500_000;
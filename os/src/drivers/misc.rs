@@ -1,7 +1,7 @@
 use core::ptr::NonNull;
 use riscv::register::mhartid;
 use volatile::VolatilePtr;
-use crate::config::{SYSTEM_RESET_BASE_ADDR, TIMER_ADDR};
+use crate::config::{MSIP_BASE_ADDR, SYSTEM_RESET_BASE_ADDR, TIMER_ADDR, TIMER_CMP_BASE_ADDR};
 
 pub enum SystemResetOp {
     ShutdownNormal,
@@ -45,4 +45,21 @@ pub unsafe fn set_mtime_cmp(addr: usize, time: usize) {
         VolatilePtr::new(NonNull::new_unchecked(addr as *mut usize))
     };
     ptr.write(time);
-}
\ No newline at end of file
+}
+
+/// The time at which the next timer interrupt of `hart` is due.
+pub unsafe fn get_mtime_cmp(hart: usize) -> usize {
+    let ptr = unsafe {
+        VolatilePtr::new(NonNull::new_unchecked((TIMER_CMP_BASE_ADDR + (hart << 3)) as *mut usize))
+    };
+    ptr.read()
+}
+
+/// Raises a machine software interrupt on `hart`, which its M-mode handler clears and passes
+/// on to S-mode.
+pub unsafe fn send_soft_interrupt(hart: usize) {
+    let ptr = unsafe {
+        VolatilePtr::new(NonNull::new_unchecked((MSIP_BASE_ADDR + (hart << 2)) as *mut u32))
+    };
+    ptr.write(1);
+}
@@ -1,2 +1,3 @@
 pub mod uart;
-pub mod misc;
\ No newline at end of file
+pub mod misc;
+pub mod plic;
\ No newline at end of file
@@ -0,0 +1,178 @@
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ptr::NonNull;
+use lazy_static::lazy_static;
+use volatile::VolatilePtr;
+use crate::config::{CPUS, PLIC_BASE_ADDR};
+use crate::ipc::notification::Notification;
+use crate::red_msg;
+use crate::sync::spin::SpinLock;
+
+/// Number of interrupt sources wired to the PLIC of QEMU virt, source 0 does not exist.
+pub const MAX_IRQ: usize = 96;
+
+const PRIORITY_OFFSET: usize = 0x0;
+const ENABLE_OFFSET: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT_OFFSET: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+
+fn register(offset: usize) -> VolatilePtr<'static, u32> {
+    unsafe { VolatilePtr::new(NonNull::new_unchecked((PLIC_BASE_ADDR + offset) as *mut u32)) }
+}
+
+/// The PLIC context through which `hart` takes interrupts in S-mode. Even contexts belong
+/// to M-mode.
+pub fn supervisor_context(hart: usize) -> usize {
+    2 * hart + 1
+}
+
+/// Sets the priority of `irq`. Priority 0 never interrupts.
+pub fn set_priority(irq: usize, priority: u32) {
+    register(PRIORITY_OFFSET + 4 * irq).write(priority);
+}
+
+pub fn enable(context: usize, irq: usize) {
+    let reg = register(ENABLE_OFFSET + ENABLE_STRIDE * context + 4 * (irq / 32));
+    reg.update(|bits| bits | 1 << (irq % 32));
+}
+
+pub fn disable(context: usize, irq: usize) {
+    let reg = register(ENABLE_OFFSET + ENABLE_STRIDE * context + 4 * (irq / 32));
+    reg.update(|bits| bits & !(1 << (irq % 32)));
+}
+
+fn set_enabled(irq: usize, enabled: bool) {
+    for hart in 0..CPUS {
+        if enabled {
+            enable(supervisor_context(hart), irq);
+        } else {
+            disable(supervisor_context(hart), irq);
+        }
+    }
+}
+
+/// Only interrupts with a priority above `threshold` reach `context`.
+pub fn set_threshold(context: usize, threshold: u32) {
+    register(CONTEXT_OFFSET + CONTEXT_STRIDE * context).write(threshold);
+}
+
+/// Takes the highest priority pending interrupt of `context`, if any.
+pub fn claim(context: usize) -> Option<usize> {
+    match register(CONTEXT_OFFSET + CONTEXT_STRIDE * context + 4).read() {
+        0 => None,
+        irq => Some(irq as usize)
+    }
+}
+
+/// Tells the PLIC that `irq`, taken by `claim`, has been handled.
+pub fn complete(context: usize, irq: usize) {
+    register(CONTEXT_OFFSET + CONTEXT_STRIDE * context + 4).write(irq as u32);
+}
+
+type IrqHandler = fn();
+
+/// An interrupt source handed to a user-space driver, which is told through a notification.
+struct IrqRoute {
+    notification: Weak<Notification>,
+    bits: usize
+}
+
+#[derive(Default)]
+struct IrqLine {
+    /// Kernel driver of the source, used while no user-space driver has taken it over.
+    handler: Option<IrqHandler>,
+    route: Option<IrqRoute>
+}
+
+impl IrqLine {
+    fn routed_to(&self) -> Option<(Arc<Notification>, usize)> {
+        let route = self.route.as_ref()?;
+        Some((route.notification.upgrade()?, route.bits))
+    }
+}
+
+lazy_static! {
+    static ref IRQ_LINES: SpinLock<Vec<IrqLine>> =
+        SpinLock::new((0..MAX_IRQ).map(|_| IrqLine::default()).collect());
+}
+
+/// Lets `hart` take every enabled interrupt in S-mode.
+pub fn init_hart(hart: usize) {
+    set_threshold(supervisor_context(hart), 0);
+}
+
+/// Installs `handler` for `irq` and enables the source on every hart. Returns false if
+/// `irq` is out of range or already has a handler.
+pub fn register_irq(irq: usize, priority: u32, handler: IrqHandler) -> bool {
+    let mut lines = IRQ_LINES.exclusive_access();
+    if irq == 0 || irq >= MAX_IRQ || lines[irq].handler.is_some() {
+        return false;
+    }
+    lines[irq].handler = Some(handler);
+    set_priority(irq, priority);
+    set_enabled(irq, true);
+    true
+}
+
+/// Makes `irq` signal `bits` on `notification` instead of running the kernel handler. The
+/// source is masked each time it fires until `ack_irq` is called. Returns false if `irq` is
+/// already routed to a live notification.
+pub fn route_irq(irq: usize, notification: &Arc<Notification>, bits: usize) -> bool {
+    let mut lines = IRQ_LINES.exclusive_access();
+    if lines[irq].routed_to().is_some() {
+        return false;
+    }
+    lines[irq].route = Some(IrqRoute { notification: Arc::downgrade(notification), bits });
+    if lines[irq].handler.is_none() {
+        set_priority(irq, 1);
+    }
+    set_enabled(irq, true);
+    true
+}
+
+/// Unmasks `irq` once its user-space driver has handled it. Returns false if it is not
+/// routed to user space.
+pub fn ack_irq(irq: usize) -> bool {
+    if IRQ_LINES.exclusive_access()[irq].routed_to().is_none() {
+        return false;
+    }
+    set_enabled(irq, true);
+    true
+}
+
+/// Gives sources whose notification is gone back to their kernel driver, if they have one.
+pub fn release_dead_routes() {
+    let mut lines = IRQ_LINES.exclusive_access();
+    for (irq, line) in lines.iter_mut().enumerate() {
+        if line.route.is_some() && line.routed_to().is_none() {
+            line.route = None;
+            set_enabled(irq, line.handler.is_some());
+        }
+    }
+}
+
+/// Claims and dispatches every interrupt pending for `hart`.
+pub fn handle_external_irq(hart: usize) {
+    let context = supervisor_context(hart);
+    while let Some(irq) = claim(context) {
+        let (route, handler) = match IRQ_LINES.exclusive_access().get(irq) {
+            Some(line) => (line.routed_to(), line.handler),
+            None => (None, None)
+        };
+        if let Some((notification, bits)) = route {
+            // The device keeps the line raised until the driver has served it.
+            set_enabled(irq, false);
+            complete(context, irq);
+            notification.signal(bits);
+            continue;
+        }
+        match handler {
+            Some(handler) => handler(),
+            None => {
+                red_msg!("[kernel] Unexpected external interrupt {}.", irq);
+            }
+        }
+        complete(context, irq);
+    }
+}
@@ -5,7 +5,7 @@ use volatile::access::*;
 use bitflags::bitflags;
 use lazy_static::lazy_static;
 use crate::config::UART_BASE_ADDR;
-use crate::sync::up::UPSafeCell;
+use crate::sync::spin::SpinLock;
 
 macro_rules! wait_for {
     ($cond: expr) => {
@@ -24,11 +24,9 @@ pub struct UartPort {
 }
 
 lazy_static! {
-    pub static ref UART: UPSafeCell<UartPort> = unsafe {
-        UPSafeCell::new(UartPort{
-            buffer: VecDeque::new()
-        })
-    };
+    pub static ref UART: SpinLock<UartPort> = SpinLock::new(UartPort{
+        buffer: VecDeque::new()
+    });
 }
 
 bitflags! {
@@ -165,8 +163,8 @@ impl UartPort {
         // Disable DLAB, and set data length to 8 bits without check
         ReadPort::LCR.write(LineControl::DATA_8b);
 
-        // Enable FIFO, clear all queues and set interrupt watermark to 14 bytes
-        WritePort::FCR.write(FifoControl::ENABLE_FIFO | FifoControl::BUF_14B |
+        // Enable FIFO, clear all queues and interrupt on every received byte
+        WritePort::FCR.write(FifoControl::ENABLE_FIFO | FifoControl::BUF_1B |
             FifoControl::CLEAR_RX_FIFO | FifoControl::CLEAR_TX_FIFO);
 
         // Set terminal ready and enable auxiliary output 2 (i.e. interrupt enable)
@@ -192,28 +190,17 @@ impl UartPort {
     }
 
     pub fn recv(&self) -> Option<u8> {
-        let lsr = &ReadPort::LSR;
-        let rbr = &ReadPort::RBR;
-        /*if (lsr.read() & LineStatus::DATA_AVAILABLE).bits() != 0 {
-            Some(rbr.read())
+        if ReadPort::LSR.read().contains(LineStatus::DATA_AVAILABLE) {
+            Some(ReadPort::RBR.read())
         } else {
             None
-        }*/
-        while ((lsr.read() & LineStatus::DATA_AVAILABLE).bits() == 0) {
-            
         }
-        Some(rbr.read())
     }
 }
 
-pub fn read() -> u8 {
-    /*loop {
-        if let Some(ch) = UART.exclusive_access().buffer.pop_front() {
-            return ch;
-        }
-        suspend_current_and_run_next()
-    }*/
-    UART.exclusive_access().recv().unwrap()
+/// Takes the oldest byte received by `handle_irq`.
+pub fn pop_input() -> Option<u8> {
+    UART.exclusive_access().buffer.pop_front()
 }
 
 pub fn write(data: u8) {
@@ -1 +1,2 @@
 pub mod stdout;
+pub mod tty;
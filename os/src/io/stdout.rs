@@ -1,6 +1,10 @@
 use core::fmt;
 use core::fmt::Write;
-use crate::drivers::uart::{read, write};
+use crate::drivers::uart::write;
+use crate::sync::spin::SpinLock;
+
+/// Keeps the lines printed by different harts apart.
+static PRINT_LOCK: SpinLock<()> = SpinLock::new(());
 
 pub struct Stdout;
 
@@ -13,11 +17,8 @@ impl Write for Stdout {
     }
 }
 
-pub fn getchar() -> u8 {
-    read()
-}
-
 pub fn print(args: fmt::Arguments) {
+    let _guard = PRINT_LOCK.exclusive_access();
     Stdout.write_fmt(args).unwrap();
 }
 
@@ -0,0 +1,175 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+use crate::config::UART_IRQ;
+use crate::drivers::plic::register_irq;
+use crate::drivers::uart::{handle_irq, pop_input, write};
+use crate::sync::spin::SpinLock;
+use crate::task::manager::pid2process;
+use crate::task::signal::{send_signal, SIGINT};
+use crate::task::task::TaskControlBlock;
+use crate::task::wait_queue::WaitQueue;
+
+const LF: u8 = b'\n';
+const CR: u8 = b'\r';
+const BS: u8 = 0x08;
+const DEL: u8 = 0x7f;
+const CTRL_C: u8 = 0x03;
+const CTRL_D: u8 = 0x04;
+
+/// Local mode flags, numbered like their Linux `c_lflag` counterparts.
+pub const ISIG: usize = 1 << 0;
+pub const ICANON: usize = 1 << 1;
+pub const ECHO: usize = 1 << 3;
+
+/// Line discipline of the console. In canonical mode input is edited a line at a time and
+/// only handed to readers once the line is finished, otherwise every byte is passed through.
+pub struct Tty {
+    mode: usize,
+    /// The line being edited in canonical mode.
+    line: VecDeque<u8>,
+    /// Bytes readers may take.
+    ready: VecDeque<u8>,
+    /// Ctrl-D on an empty line, makes the next read that finds nothing ready return 0.
+    eof: bool,
+    readers: WaitQueue,
+    /// Process that Ctrl-C interrupts in `ISIG` mode.
+    foreground: Option<usize>,
+    /// Ctrl-C was typed and the foreground process has not been sent SIGINT yet.
+    interrupted: bool
+}
+
+lazy_static! {
+    pub static ref TTY: SpinLock<Tty> = SpinLock::new(Tty::new());
+}
+
+impl Tty {
+    pub fn new() -> Self {
+        Self {
+            mode: ISIG | ICANON | ECHO,
+            line: VecDeque::new(),
+            ready: VecDeque::new(),
+            eof: false,
+            readers: WaitQueue::new(),
+            foreground: None,
+            interrupted: false
+        }
+    }
+    pub fn mode(&self) -> usize {
+        self.mode
+    }
+    /// Switching out of canonical mode hands the unfinished line to readers as it is.
+    pub fn set_mode(&mut self, mode: usize) {
+        self.mode = mode;
+        if mode & ICANON == 0 {
+            self.ready.extend(self.line.drain(..));
+            self.readers.wake_all();
+        }
+    }
+    /// Makes Ctrl-C interrupt process `pid`, or no process if it is 0.
+    pub fn set_foreground(&mut self, pid: usize) {
+        self.foreground = (pid != 0).then_some(pid);
+    }
+    fn echo(&self, data: u8) {
+        if self.mode & ECHO != 0 {
+            write(data);
+        }
+    }
+    fn receive(&mut self, data: u8) {
+        let data = if data == CR { LF } else { data };
+        if data == CTRL_C && self.mode & ISIG != 0 {
+            self.line.clear();
+            self.echo(b'^');
+            self.echo(b'C');
+            self.echo(LF);
+            self.interrupted = true;
+            return;
+        }
+        if self.mode & ICANON == 0 {
+            self.echo(data);
+            self.ready.push_back(data);
+            return;
+        }
+        match data {
+            BS | DEL => {
+                if self.line.pop_back().is_some() {
+                    // The UART driver turns a backspace into "\b \b".
+                    self.echo(BS);
+                }
+            }
+            CTRL_C => {
+                self.line.clear();
+                self.echo(b'^');
+                self.echo(b'C');
+                self.echo(LF);
+            }
+            CTRL_D => {
+                if self.line.is_empty() {
+                    self.eof = true;
+                } else {
+                    self.ready.extend(self.line.drain(..));
+                }
+            }
+            LF => {
+                self.echo(LF);
+                self.line.push_back(LF);
+                self.ready.extend(self.line.drain(..));
+            }
+            _ => {
+                self.echo(data);
+                self.line.push_back(data);
+            }
+        }
+    }
+    /// Moves up to `buf.len()` ready bytes into `buf`, stopping after a line feed in canonical
+    /// mode. Returns None if the caller has to wait for more input.
+    pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.ready.is_empty() {
+            if self.eof {
+                self.eof = false;
+                return Some(0);
+            }
+            return None;
+        }
+        let mut len = 0;
+        while len < buf.len() {
+            let Some(data) = self.ready.pop_front() else {
+                break;
+            };
+            buf[len] = data;
+            len += 1;
+            if data == LF && self.mode & ICANON != 0 {
+                break;
+            }
+        }
+        Some(len)
+    }
+    pub fn wait(&mut self, task: Arc<TaskControlBlock>) {
+        self.readers.push(task);
+    }
+}
+
+/// Feeds the console UART's receive interrupts into the line discipline.
+pub fn init_tty() {
+    assert!(register_irq(UART_IRQ, 1, || {
+        handle_irq();
+        handle_input();
+    }));
+}
+
+/// Runs the bytes the UART has received through the line discipline.
+fn handle_input() {
+    let mut tty = TTY.exclusive_access();
+    while let Some(data) = pop_input() {
+        tty.receive(data);
+    }
+    if !tty.ready.is_empty() || tty.eof {
+        tty.readers.wake_all();
+    }
+    let interrupted = core::mem::take(&mut tty.interrupted);
+    let foreground = tty.foreground;
+    drop(tty);
+    if let Some(process) = foreground.filter(|_| interrupted).and_then(pid2process) {
+        send_signal(&process, SIGINT);
+    }
+}
@@ -0,0 +1,145 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use crate::cap::Capability;
+use crate::ipc::{abort_call, message, receive_cap, set_message, IpcMessage, NOTIFICATION_BADGE};
+use crate::ipc::notification::Notification;
+use crate::sync::spin::SpinLock;
+use crate::task::{block_current_and_run_next, wakeup_task};
+use crate::task::task::TaskControlBlock;
+
+/// A sender blocked on an endpoint. Its message stays in its trap context until a receiver
+/// picks it up.
+struct Sender {
+    task: Arc<TaskControlBlock>,
+    badge: usize,
+    is_call: bool,
+    cap: Option<Capability>
+}
+
+/// A rendezvous point for synchronous IPC. A message is only transferred once both a sender
+/// and a receiver are there, whichever side comes first blocks in the endpoint's queue.
+pub struct Endpoint {
+    inner: SpinLock<EndpointInner>
+}
+
+struct EndpointInner {
+    senders: VecDeque<Sender>,
+    receivers: VecDeque<Arc<TaskControlBlock>>,
+    /// Also wakes receivers, so that a server can wait for messages and events at once.
+    notification: Option<Arc<Notification>>
+}
+
+impl EndpointInner {
+    /// Threads of a process that exited while they waited here are skipped.
+    fn pop_receiver(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.receivers.retain(|receiver| !receiver.is_exited());
+        self.receivers.pop_front()
+    }
+    fn pop_sender(&mut self) -> Option<Sender> {
+        self.senders.retain(|sender| !sender.task.is_exited());
+        self.senders.pop_front()
+    }
+}
+
+/// Hands `msg` and `cap` to `receiver`, whose receive returns `badge`. A caller is
+/// remembered so that the receiver can reply to it.
+fn deliver(sender: &Arc<TaskControlBlock>, receiver: &Arc<TaskControlBlock>,
+           msg: IpcMessage, badge: usize, is_call: bool, cap: Option<Capability>) {
+    // The receiver is blocked, so its trap context stays put without holding its lock.
+    let cx = receiver.inner_exclusive_access().get_trap_cx();
+    set_message(cx, msg);
+    receive_cap(receiver, cx, cap);
+    let mut receiver_inner = receiver.inner_exclusive_access();
+    receiver_inner.ipc.result = badge as isize;
+    let unanswered = if is_call {
+        receiver_inner.ipc.reply_to.replace(sender.clone())
+    } else {
+        None
+    };
+    drop(receiver_inner);
+    if let Some(caller) = unanswered {
+        abort_call(caller);
+    }
+}
+
+impl Endpoint {
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(EndpointInner {
+                senders: VecDeque::new(),
+                receivers: VecDeque::new(),
+                notification: None
+            })
+        }
+    }
+    /// Sends `msg` and `cap` from `task`, blocking until a receiver takes them. A call then
+    /// keeps waiting for the reply, which is left in `task`'s message registers.
+    pub fn send(&self, task: &Arc<TaskControlBlock>, msg: IpcMessage, badge: usize,
+                is_call: bool, cap: Option<Capability>) -> isize {
+        let mut inner = self.inner.exclusive_access();
+        if let Some(receiver) = inner.pop_receiver() {
+            drop(inner);
+            deliver(task, &receiver, msg, badge, is_call, cap);
+            wakeup_task(receiver);
+            if !is_call {
+                return 0;
+            }
+        } else {
+            inner.senders.push_back(Sender { task: task.clone(), badge, is_call, cap });
+            drop(inner);
+        }
+        block_current_and_run_next();
+        task.inner_exclusive_access().ipc.result
+    }
+    /// Receives a message into `task`'s message registers, blocking until there is a sender.
+    /// Returns the sender's badge, or `NOTIFICATION_BADGE` if the bound notification was
+    /// signalled first.
+    pub fn recv(&self, task: &Arc<TaskControlBlock>) -> isize {
+        let mut inner = self.inner.exclusive_access();
+        let pending = inner.notification.as_ref().map_or(0, |notification| notification.poll());
+        if pending != 0 {
+            drop(inner);
+            let cx = task.inner_exclusive_access().get_trap_cx();
+            set_message(cx, [pending, 0, 0, 0]);
+            receive_cap(task, cx, None);
+            return NOTIFICATION_BADGE;
+        }
+        if let Some(sender) = inner.pop_sender() {
+            drop(inner);
+            let msg = message(sender.task.inner_exclusive_access().get_trap_cx());
+            deliver(&sender.task, task, msg, sender.badge, sender.is_call, sender.cap);
+            if !sender.is_call {
+                sender.task.inner_exclusive_access().ipc.result = 0;
+                wakeup_task(sender.task);
+            }
+        } else {
+            inner.receivers.push_back(task.clone());
+            drop(inner);
+            block_current_and_run_next();
+        }
+        task.inner_exclusive_access().ipc.result
+    }
+    /// Takes a receiver blocked on this endpoint together with the pending bits of the bound
+    /// notification, if there are both. The endpoint lock is held while the bits are taken, so
+    /// that a receiver either finds them in `recv` or is handed them here.
+    pub fn take_notified_receiver(&self) -> Option<(Arc<TaskControlBlock>, usize)> {
+        let mut inner = self.inner.exclusive_access();
+        let notification = inner.notification.clone()?;
+        let receiver = inner.pop_receiver()?;
+        let word = notification.poll();
+        if word == 0 {
+            inner.receivers.push_front(receiver);
+            return None;
+        }
+        Some((receiver, word))
+    }
+    /// Returns false if the endpoint already has a notification.
+    pub fn bind_notification(self: &Arc<Self>, notification: Arc<Notification>) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if inner.notification.is_some() || !notification.bind(self) {
+            return false;
+        }
+        inner.notification = Some(notification);
+        true
+    }
+}
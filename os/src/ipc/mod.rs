@@ -0,0 +1,80 @@
+use alloc::sync::Arc;
+use crate::cap::Capability;
+use crate::syscall::errno::EPIPE;
+use crate::task::task::TaskControlBlock;
+use crate::task::wakeup_task;
+use crate::trap::context::TrapContext;
+
+pub mod endpoint;
+pub mod notification;
+
+/// Number of words in a message, carried in registers a1 to a4.
+pub const MSG_WORDS: usize = 4;
+const MSG_REG: usize = 11;
+/// A capability transferred along with a message is named by its slot in a5, on the
+/// sending side as well as the receiving one.
+const CAP_REG: usize = 15;
+
+/// Slot number meaning that no capability is transferred.
+pub const NO_CAP: usize = usize::MAX;
+
+pub type IpcMessage = [usize; MSG_WORDS];
+
+/// What a receive returns when it was woken by the endpoint's bound notification, with the
+/// notification word as the first message word. Capabilities cannot carry this badge.
+pub const NOTIFICATION_BADGE: isize = isize::MAX;
+
+/// Per-task IPC bookkeeping.
+#[derive(Default)]
+pub struct IpcState {
+    /// The caller waiting for this task's `reply`, set when it receives a call.
+    pub reply_to: Option<Arc<TaskControlBlock>>,
+    /// What the pending send, receive or call returns once the task is woken up.
+    pub result: isize
+}
+
+pub fn message(cx: &TrapContext) -> IpcMessage {
+    cx.reg[MSG_REG..MSG_REG + MSG_WORDS].try_into().unwrap()
+}
+
+pub fn set_message(cx: &mut TrapContext, msg: IpcMessage) {
+    cx.reg[MSG_REG..MSG_REG + MSG_WORDS].copy_from_slice(&msg);
+}
+
+/// Puts a capability that came with a message into a free slot of the receiver and tells
+/// it which one, or `NO_CAP` if there was none or no room for it. Takes the receiver's
+/// process lock, so the receiver's own lock must not be held.
+pub fn receive_cap(receiver: &TaskControlBlock, cx: &mut TrapContext, cap: Option<Capability>) {
+    let slot = cap.zip(receiver.process.upgrade())
+        .and_then(|(cap, process)| process.inner_exclusive_access().cspace.insert(cap))
+        .unwrap_or(NO_CAP);
+    cx.reg[CAP_REG] = slot;
+}
+
+/// Wakes up a blocked caller with `msg`, the capability sent along and `result`.
+fn resume_caller(caller: Arc<TaskControlBlock>, msg: Option<(IpcMessage, Option<Capability>)>, result: isize) {
+    if let Some((msg, cap)) = msg {
+        let cx = caller.inner_exclusive_access().get_trap_cx();
+        set_message(cx, msg);
+        receive_cap(&caller, cx, cap);
+    }
+    let mut caller_inner = caller.inner_exclusive_access();
+    caller_inner.ipc.result = result;
+    drop(caller_inner);
+    wakeup_task(caller);
+}
+
+/// Answers the call the current task received last.
+/// Returns false if there is no caller waiting for a reply.
+pub fn reply(task: &Arc<TaskControlBlock>, msg: IpcMessage, cap: Option<Capability>) -> bool {
+    let Some(caller) = task.inner_exclusive_access().ipc.reply_to.take() else {
+        return false;
+    };
+    resume_caller(caller, Some((msg, cap)), 0);
+    true
+}
+
+/// Fails a call that will never be replied to, e.g. because the receiver exited.
+pub fn abort_call(caller: Arc<TaskControlBlock>) {
+    resume_caller(caller, None, -EPIPE);
+}
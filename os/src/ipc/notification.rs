@@ -0,0 +1,83 @@
+use alloc::collections::VecDeque;
+use alloc::sync::{Arc, Weak};
+use crate::ipc::endpoint::Endpoint;
+use crate::ipc::{receive_cap, set_message, NOTIFICATION_BADGE};
+use crate::sync::spin::SpinLock;
+use crate::task::{block_current_and_run_next, wakeup_task};
+use crate::task::task::TaskControlBlock;
+
+/// A word of event bits for asynchronous signalling. Signalling never blocks: the bits are
+/// ORed into the word and handed to the first waiter, or to a receiver blocked on the bound
+/// endpoint, as soon as there is one.
+/// An endpoint's lock is always taken before the lock of its bound notification.
+pub struct Notification {
+    inner: SpinLock<NotificationInner>
+}
+
+struct NotificationInner {
+    word: usize,
+    waiters: VecDeque<Arc<TaskControlBlock>>,
+    endpoint: Option<Weak<Endpoint>>
+}
+
+impl Notification {
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(NotificationInner {
+                word: 0,
+                waiters: VecDeque::new(),
+                endpoint: None
+            })
+        }
+    }
+    pub fn signal(&self, bits: usize) {
+        let mut inner = self.inner.exclusive_access();
+        inner.word |= bits;
+        inner.waiters.retain(|waiter| !waiter.is_exited());
+        if let Some(waiter) = inner.waiters.pop_front() {
+            let word = core::mem::take(&mut inner.word);
+            drop(inner);
+            waiter.inner_exclusive_access().ipc.result = word as isize;
+            wakeup_task(waiter);
+            return;
+        }
+        let Some(endpoint) = inner.endpoint.as_ref().and_then(|endpoint| endpoint.upgrade()) else {
+            return;
+        };
+        // The endpoint takes the bits again under its own lock, unless `recv` or another
+        // signal got to them first.
+        drop(inner);
+        if let Some((receiver, word)) = endpoint.take_notified_receiver() {
+            let cx = receiver.inner_exclusive_access().get_trap_cx();
+            set_message(cx, [word, 0, 0, 0]);
+            receive_cap(&receiver, cx, None);
+            receiver.inner_exclusive_access().ipc.result = NOTIFICATION_BADGE;
+            wakeup_task(receiver);
+        }
+    }
+    /// Takes the pending bits without blocking, 0 if there are none.
+    pub fn poll(&self) -> usize {
+        core::mem::take(&mut self.inner.exclusive_access().word)
+    }
+    /// Blocks `task` until some bits are pending, then takes them.
+    pub fn wait(&self, task: &Arc<TaskControlBlock>) -> isize {
+        let mut inner = self.inner.exclusive_access();
+        if inner.word != 0 {
+            return core::mem::take(&mut inner.word) as isize;
+        }
+        inner.waiters.push_back(task.clone());
+        drop(inner);
+        block_current_and_run_next();
+        task.inner_exclusive_access().ipc.result
+    }
+    /// Lets receivers on `endpoint` be woken by this notification.
+    /// Returns false if it is already bound.
+    pub fn bind(&self, endpoint: &Arc<Endpoint>) -> bool {
+        let mut inner = self.inner.exclusive_access();
+        if inner.endpoint.as_ref().is_some_and(|bound| bound.strong_count() > 0) {
+            return false;
+        }
+        inner.endpoint = Some(Arc::downgrade(endpoint));
+        true
+    }
+}
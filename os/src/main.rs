@@ -6,7 +6,9 @@ extern crate alloc;
 mod lang_items;
 mod drivers;
 pub mod config;
+mod cap;
 mod io;
+mod ipc;
 mod sync;
 mod trap;
 mod syscall;
@@ -17,21 +19,38 @@ mod utils;
 mod mem;
 
 use core::arch::{asm, global_asm};
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, Ordering};
 use riscv::register::{mepc, mstatus, pmpaddr0, pmpcfg0, satp, sie};
 use riscv::register::mstatus::MPP;
 use riscv::register::satp::Satp;
+use crate::drivers::misc::hart_id;
+use crate::drivers::plic::init_hart;
+use crate::io::tty::init_tty;
 use crate::drivers::uart::UART;
 use crate::loader::list_apps;
 use crate::mem::frame_allocator::{frame_allocator_test, init_frame_allocator};
 use crate::mem::heap_allocator::{heap_test, init_heap};
 use crate::mem::memory_set::{remap_test, KERNEL_SPACE};
 use crate::task::add_initproc;
-use crate::task::processor::run_tasks;
+use crate::task::processor::{current_hart, run_tasks};
 use crate::timer::init_timer;
 
-global_asm!(include_str!("asm/entry.asm"));
+global_asm!(
+    include_str!("asm/entry.asm"),
+    CPUS = const config::CPUS,
+    BOOT_STACK_SIZE = const config::BOOT_STACK_SIZE
+);
 global_asm!(include_str!("asm/link_app.asm"));
 
+/// Hart that initialises the kernel while the others wait.
+const BOOT_HART: usize = 0;
+
+/// Set by the boot hart once the kernel is initialised. Like the boot stacks it is outside
+/// the part of `.bss` that gets cleared.
+#[unsafe(link_section = ".bss.stack")]
+static BOOTED: AtomicBool = AtomicBool::new(false);
+
 unsafe fn clear_bss() {
     unsafe extern "C" {
         fn sbss();
@@ -51,6 +70,8 @@ pub unsafe fn sbi_entry() -> ! {
         pmpaddr0::write(0x3fffffffffffffusize);
         pmpcfg0::write(0xf);
         init_timer();
+        // The kernel finds the hart it runs on in `tp`.
+        asm!("mv tp, {}", in(reg) hart_id());
         asm!(
             "csrw mideleg, {mideleg}",
             "csrw medeleg, {medeleg}",
@@ -63,6 +84,13 @@ pub unsafe fn sbi_entry() -> ! {
 
 #[unsafe(no_mangle)]
 pub unsafe fn rust_main() -> ! {
+    let hart = current_hart();
+    if hart != BOOT_HART {
+        while !BOOTED.load(Ordering::Acquire) {
+            spin_loop();
+        }
+        unsafe { start_hart(hart); }
+    }
     init_uart();
     unsafe {
         clear_bss();
@@ -81,13 +109,28 @@ pub unsafe fn rust_main() -> ! {
     green_msg!("[kernel] Remap test passed!");
     unsafe { trap::init_trap(); }
     green_msg!("[kernel] Trap info set correctly.");
+    init_hart(hart);
+    init_tty();
+    green_msg!("[kernel] External interrupts routed to S-mode.");
     list_apps();
     add_initproc();
+    BOOTED.store(true, Ordering::Release);
+    unsafe { start_hart(hart); }
+}
+
+/// Sets up the parts of `hart` that the boot hart cannot do for it and starts scheduling.
+unsafe fn start_hart(hart: usize) -> ! {
+    if hart != BOOT_HART {
+        KERNEL_SPACE.exclusive_access().activate();
+        unsafe { trap::init_trap(); }
+        init_hart(hart);
+    }
     unsafe {
         sie::set_stimer();
         sie::set_sext();
         sie::set_ssoft();
     }
+    green_msg!("[kernel] Hart {} started.", hart);
     run_tasks();
     unreachable!();
 }
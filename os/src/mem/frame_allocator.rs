@@ -2,7 +2,7 @@ use alloc::vec::Vec;
 use lazy_static::lazy_static;
 use crate::config::MEMORY_END;
 use crate::mem::address::{PhysAddr, PhysPageNum};
-use crate::sync::up::UPSafeCell;
+use crate::sync::spin::SpinLock;
 
 trait FrameAllocator {
     fn new() -> Self;
@@ -23,9 +23,8 @@ pub struct StackFrameAllocator {
 type FrameAllocatorImpl = StackFrameAllocator;
 
 lazy_static!{
-    pub static ref FRAME_ALLOCATOR: UPSafeCell<FrameAllocatorImpl> = unsafe {
-        UPSafeCell::new(FrameAllocatorImpl::new())
-    };
+    pub static ref FRAME_ALLOCATOR: SpinLock<FrameAllocatorImpl> =
+        SpinLock::new(FrameAllocatorImpl::new());
 }
 
 impl FrameTracker {
@@ -1,18 +1,22 @@
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec::Vec;
 use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use bitflags::bitflags;
 use lazy_static::lazy_static;
 use alloc::sync::Arc;
 use riscv::register::satp;
 use riscv::register::satp::Satp;
 use crate::blue_msg;
-use crate::config::{MEMORY_END, SYSTEM_RESET_BASE_ADDR, TIMER_ADDR, TRAMPOLINE, TRAP_CONTEXT, UART_BASE_ADDR};
+use crate::config::{CLINT_BASE_ADDR, CLINT_SIZE, MEMORY_END, MMAP_BASE, MMAP_END, PLIC_BASE_ADDR, PLIC_SIZE, SYSTEM_RESET_BASE_ADDR, TRAMPOLINE, TRAP_CONTEXT, UART_BASE_ADDR, USER_STACK_LIMIT, USER_STACK_SIZE, USER_STACK_TOP};
 use crate::mem::address::{PageTableEntry, PhysAddr, PhysPageNum, StepByOne, VPNRange, VirtAddr, VirtPageNum, PAGE_SIZE};
 use crate::mem::frame_allocator::{frame_alloc, FrameTracker};
 use crate::mem::memory_set::MapType::{Identical, Framed};
-use crate::mem::page_table::{PTEFlags, PageTable};
-use crate::sync::up::UPSafeCell;
+use crate::mem::page_table::{translated_byte_buffer, PTEFlags, PageTable};
+use crate::sync::spin::SpinLock;
+use crate::task::processor::current_hart;
+use crate::trap::ipi::remote_sfence_vma;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MapType {
@@ -20,7 +24,7 @@ pub enum MapType {
 }
 
 bitflags! {
-    #[derive(Copy, Clone)]
+    #[derive(Copy, Clone, PartialEq)]
     pub struct MapPermission: u8 {
         const R = 1 << 1;
         const W = 1 << 2;
@@ -29,22 +33,35 @@ bitflags! {
     }
 }
 
+/// Program header type the linker uses to record the requested stack size.
+const PT_GNU_STACK: u32 = 0x6474_e551;
+
 pub struct MapArea {
     vpn_range: VPNRange,
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// Frames are reference counted so that a forked child can share them copy-on-write.
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     map_type: MapType,
-    map_permission: MapPermission
+    map_permission: MapPermission,
+    /// Lazy framed areas leave their PTEs invalid until the first access faults them in.
+    lazy: bool,
+    /// Set for areas mapping frames shared with other address spaces, which are never copied
+    /// on write. Holds the most permissive mapping the frames' owner allows.
+    share_limit: Option<MapPermission>,
+    /// Set for the user stack of the thread with this tid, see `map_thread_stack`.
+    thread_stack: Option<usize>
 }
 
 pub struct MemorySet {
     page_table: PageTable,
-    areas: Vec<MapArea>
+    areas: Vec<MapArea>,
+    /// Harts that may have translations of this address space cached, as a bit mask.
+    active_harts: AtomicUsize
 }
 
 lazy_static! {
-    pub static ref KERNEL_SPACE: Arc<UPSafeCell<MemorySet>> = unsafe { Arc::new(
-        UPSafeCell::new(MemorySet::new_kernel())
-    )};
+    pub static ref KERNEL_SPACE: Arc<SpinLock<MemorySet>> = Arc::new(
+        SpinLock::new(MemorySet::new_kernel())
+    );
 }
 
 impl MapArea {
@@ -55,7 +72,17 @@ impl MapArea {
             vpn_range: VPNRange::new(start_vpn, end_vpn),
             data_frames: BTreeMap::new(),
             map_type,
-            map_permission
+            map_permission,
+            lazy: false,
+            share_limit: None,
+            thread_stack: None
+        }
+    }
+    /// A framed area whose pages are allocated zeroed on first access.
+    pub fn new_lazy(start_va: VirtAddr, end_va: VirtAddr, map_permission: MapPermission) -> Self {
+        Self {
+            lazy: true,
+            ..Self::new(start_va, end_va, Framed, map_permission)
         }
     }
     pub fn from_another(another: &Self) -> Self {
@@ -63,49 +90,132 @@ impl MapArea {
             vpn_range: VPNRange::new(another.vpn_range.start(), another.vpn_range.end()),
             data_frames: BTreeMap::new(),
             map_type: another.map_type,
-            map_permission: another.map_permission
+            map_permission: another.map_permission,
+            lazy: another.lazy,
+            share_limit: another.share_limit,
+            thread_stack: another.thread_stack
         }
     }
-    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        let ppn: PhysPageNum;
-        match self.map_type {
-            Identical => {
-                ppn = PhysPageNum(vpn.0);
-            }
+    /// Returns false if there is no frame left for the page or its page tables.
+    pub fn map_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> bool {
+        let (ppn, frame) = match self.map_type {
+            Identical => (PhysPageNum(vpn.0), None),
             Framed => {
-                let frame = frame_alloc().unwrap();
-                ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+                let Some(frame) = frame_alloc() else {
+                    return false;
+                };
+                (frame.ppn, Some(Arc::new(frame)))
             }
+        };
+        if !page_table.try_map(vpn, ppn, self.pte_flags()) {
+            return false;
+        }
+        if let Some(frame) = frame {
+            self.data_frames.insert(vpn, frame);
         }
-        let pte_flags = PTEFlags::from_bits(self.map_permission.bits()).unwrap();
-        page_table.map(vpn, ppn, pte_flags);
+        true
     }
     pub fn map(&mut self, page_table: &mut PageTable) {
+        if self.lazy {
+            return;
+        }
         for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+            assert!(self.map_one(page_table, vpn), "Out of frames mapping vpn {:#x}.", vpn.0);
         }
     }
-    pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        match self.map_type {
-            Identical => {}
-            Framed => {
-                self.data_frames.remove(&vpn);
-            }
-        }
+    /// Returns the frame that was mapped, which the caller keeps until TLBs are flushed.
+    pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> Option<Arc<FrameTracker>> {
         page_table.unmap(vpn);
+        self.data_frames.remove(&vpn)
     }
-    pub fn unmap(&mut self, page_table: &mut PageTable) {
+    /// Clears the PTEs of the area. Its frames are only freed with the area, so that a hart
+    /// still holding a stale translation cannot reach a frame that was handed out again.
+    pub fn unmap(&self, page_table: &mut PageTable) {
         for vpn in self.vpn_range {
-            self.unmap_one(page_table, vpn);
+            if self.map_type == Framed && !self.data_frames.contains_key(&vpn) {
+                continue;
+            }
+            page_table.unmap(vpn);
+        }
+    }
+    fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.start() <= vpn && vpn < self.vpn_range.end()
+    }
+    /// Areas that may share frames with a forked child. Kernel-only areas such as the trap
+    /// context are written through their physical frames, so they are always copied.
+    fn is_cow_shareable(&self) -> bool {
+        self.map_type == Framed && self.map_permission.contains(MapPermission::U) && self.share_limit.is_none()
+    }
+    fn pte_flags(&self) -> PTEFlags {
+        PTEFlags::from_bits(self.map_permission.bits()).unwrap()
+    }
+    /// Gives a writer of a copy-on-write page its own frame, or write access to the frame
+    /// if nobody else shares it anymore. Returns the frame it replaced, if any, or Err if no
+    /// frame is left for the copy.
+    fn copy_on_write(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) -> Result<Option<Arc<FrameTracker>>, ()> {
+        let frame = self.data_frames.get(&vpn).unwrap();
+        if Arc::strong_count(frame) == 1 {
+            page_table.remap(vpn, frame.ppn, self.pte_flags());
+            Ok(None)
+        } else {
+            let new_frame = frame_alloc().ok_or(())?;
+            new_frame.ppn.get_bytes_array().copy_from_slice(frame.ppn.get_bytes_array());
+            page_table.remap(vpn, new_frame.ppn, self.pte_flags());
+            Ok(self.data_frames.insert(vpn, Arc::new(new_frame)))
+        }
+    }
+    /// Moves the end of a lazy area, unmapping the pages it no longer covers.
+    /// Returns their frames.
+    fn resize(&mut self, page_table: &mut PageTable, new_end: VirtPageNum) -> Vec<Arc<FrameTracker>> {
+        assert!(self.lazy);
+        let mut removed = Vec::new();
+        for vpn in VPNRange::new(new_end.min(self.vpn_range.end()), self.vpn_range.end()) {
+            if self.data_frames.contains_key(&vpn) {
+                removed.extend(self.unmap_one(page_table, vpn));
+            }
+        }
+        self.vpn_range = VPNRange::new(self.vpn_range.start(), new_end);
+        removed
+    }
+    /// Splits the area at `at`, keeping `[start, at)` and returning `[at, end)`.
+    fn split_off(&mut self, at: VirtPageNum) -> Self {
+        let mut tail = Self::from_another(self);
+        tail.vpn_range = VPNRange::new(at, self.vpn_range.end());
+        tail.data_frames = self.data_frames.split_off(&at);
+        self.vpn_range = VPNRange::new(self.vpn_range.start(), at);
+        tail
+    }
+    /// Changes the permission of the area and of every page already mapped in it.
+    /// Pages still shared copy-on-write stay read-only until they are written.
+    fn set_permission(&mut self, page_table: &mut PageTable, map_permission: MapPermission) {
+        self.map_permission = map_permission;
+        let flags = self.pte_flags();
+        if self.map_type == Identical {
+            for vpn in self.vpn_range {
+                page_table.remap(vpn, PhysPageNum(vpn.0), flags);
+            }
+            return;
+        }
+        for (&vpn, frame) in self.data_frames.iter() {
+            if Arc::strong_count(frame) > 1 && self.share_limit.is_none() {
+                page_table.remap(vpn, frame.ppn, flags - PTEFlags::W);
+            } else {
+                page_table.remap(vpn, frame.ppn, flags);
+            }
         }
     }
-    pub fn copy_data(&mut self, page_table: &PageTable, data: &[u8]) {
+    pub fn copy_data(&mut self, page_table: &mut PageTable, data: &[u8]) {
         assert_eq!(self.map_type, MapType::Framed);
+        if data.is_empty() {
+            return;
+        }
         let mut start: usize = 0;
         let mut vpn = self.vpn_range.start();
         let len = data.len();
         loop {
+            if self.lazy && !self.data_frames.contains_key(&vpn) {
+                assert!(self.map_one(page_table, vpn), "Out of frames loading vpn {:#x}.", vpn.0);
+            }
             let src = &data[start..len.min(start + PAGE_SIZE)];
             let dst = &mut page_table.translate(vpn).unwrap().ppn().get_bytes_array()[..src.len()];
             dst.copy_from_slice(src);
@@ -135,7 +245,8 @@ impl MemorySet {
     pub fn new_bare() -> Self {
         Self {
             page_table: PageTable::new(),
-            areas: Vec::new()
+            areas: Vec::new(),
+            active_harts: AtomicUsize::new(0)
         }
     }
     pub fn new_kernel() -> Self {
@@ -182,8 +293,8 @@ impl MemorySet {
             MapPermission::R | MapPermission::W
         ), None);
         memory_set.push(MapArea::new(
-            TIMER_ADDR.into(),
-            (TIMER_ADDR + PAGE_SIZE - 1).into(),
+            CLINT_BASE_ADDR.into(),
+            (CLINT_BASE_ADDR + CLINT_SIZE).into(),
             Identical,
             MapPermission::R | MapPermission::W
         ), None);
@@ -193,19 +304,35 @@ impl MemorySet {
             Identical,
             MapPermission::R | MapPermission::W
         ), None);
+        memory_set.push(MapArea::new(
+            PLIC_BASE_ADDR.into(),
+            (PLIC_BASE_ADDR + PLIC_SIZE).into(),
+            Identical,
+            MapPermission::R | MapPermission::W
+        ), None);
         memory_set
     }
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// Returns the memory set, the user stack top, the bottom of the (still empty) heap and
+    /// the entry point.
+    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize, usize) {
         let mut memory_set = Self::new_bare();
         memory_set.map_trampoline();
         let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
         assert_eq!(elf.header.pt1.magic, [0x7f, 0x45, 0x4c, 0x46], "Invalid ELF file");
         let ph_count = elf.header.pt2.ph_count();
         let mut max_end_vpn = VirtPageNum(0);
+        let mut user_stack_size = USER_STACK_SIZE;
         for i in 0..ph_count {
             let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() != xmas_elf::program::Type::Load {
-                continue;
+            match ph.get_type().unwrap() {
+                xmas_elf::program::Type::Load => {}
+                xmas_elf::program::Type::OsSpecific(PT_GNU_STACK) if ph.mem_size() != 0 => {
+                    user_stack_size = (ph.mem_size() as usize)
+                        .next_multiple_of(PAGE_SIZE)
+                        .min(USER_STACK_LIMIT);
+                    continue;
+                }
+                _ => continue
             }
             let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
             let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
@@ -214,7 +341,7 @@ impl MemorySet {
             if ph_flags.is_read() { permission |= MapPermission::R; }
             if ph_flags.is_write() { permission |= MapPermission::W; }
             if ph_flags.is_execute() { permission |= MapPermission::X; }
-            let map_area = MapArea::new(start_va, end_va, Framed, permission);
+            let map_area = MapArea::new_lazy(start_va, end_va, permission);
             max_end_vpn = map_area.vpn_range.end();
             memory_set.push(
                 map_area,
@@ -222,12 +349,15 @@ impl MemorySet {
             );
         }
         let max_end_va: VirtAddr = max_end_vpn.into();
-        let user_stack_bottom: usize = usize::from(max_end_va) + PAGE_SIZE;
-        let user_stack_top: usize = user_stack_bottom + PAGE_SIZE;
-        memory_set.push(MapArea::new(
-            user_stack_bottom.into(),
-            user_stack_top.into(),
-            Framed,
+        let heap_bottom: usize = max_end_va.into();
+        memory_set.push(MapArea::new_lazy(
+            heap_bottom.into(),
+            heap_bottom.into(),
+            MapPermission::R | MapPermission::W | MapPermission::U
+        ), None);
+        memory_set.push(MapArea::new_lazy(
+            (USER_STACK_TOP - user_stack_size).into(),
+            USER_STACK_TOP.into(),
             MapPermission::R | MapPermission::W | MapPermission::U
         ), None);
         memory_set.push(MapArea::new(
@@ -236,12 +366,12 @@ impl MemorySet {
             Framed,
             MapPermission::R | MapPermission::W
         ), None);
-        (memory_set, user_stack_top, elf.header.pt2.entry_point() as usize)
+        (memory_set, USER_STACK_TOP, heap_bottom, elf.header.pt2.entry_point() as usize)
     }
     fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
         map_area.map(&mut self.page_table);
         if let Some(data) = data {
-            map_area.copy_data(&self.page_table, data);
+            map_area.copy_data(&mut self.page_table, data);
         }
         self.areas.push(map_area);
     }
@@ -251,18 +381,190 @@ impl MemorySet {
     ) {
         self.push(MapArea::new(start_va, end_va, Framed, permission), None);
     }
+    fn overlaps(&self, start: VirtPageNum, end: VirtPageNum) -> bool {
+        self.areas.iter().any(|area| {
+            area.vpn_range.start() < end && start < area.vpn_range.end()
+        })
+    }
+    /// Finds the lowest free range of `pages` pages in `[MMAP_BASE, MMAP_END)`.
+    pub fn find_free_area(&self, pages: usize) -> Option<VirtPageNum> {
+        let mut start = VirtAddr::from(MMAP_BASE).floor();
+        let limit = VirtAddr::from(MMAP_END).floor();
+        while start.0 + pages <= limit.0 {
+            let end = VirtPageNum(start.0 + pages);
+            match self.areas.iter().find(|area| {
+                area.vpn_range.start() < end && start < area.vpn_range.end()
+            }) {
+                Some(area) => start = area.vpn_range.end(),
+                None => return Some(start)
+            }
+        }
+        None
+    }
+    /// Maps a lazy anonymous area at `[start, end)`.
+    /// Returns false if it overlaps an existing area.
+    pub fn mmap(&mut self, start: VirtPageNum, end: VirtPageNum, permission: MapPermission) -> bool {
+        if self.overlaps(start, end) {
+            return false;
+        }
+        self.push(MapArea::new_lazy(start.into(), end.into(), permission), None);
+        true
+    }
+    /// Maps the user stack of thread `tid` like `mmap`, but remembers whose stack it is, so
+    /// that `unmap_thread_stack` leaves alone whatever the thread mapped over it in between.
+    pub fn map_thread_stack(&mut self, tid: usize, start: VirtPageNum, end: VirtPageNum, permission: MapPermission) -> bool {
+        if self.overlaps(start, end) {
+            return false;
+        }
+        let mut area = MapArea::new_lazy(start.into(), end.into(), permission);
+        area.thread_stack = Some(tid);
+        self.push(area, None);
+        true
+    }
+    /// Unmaps what is left of the user stack of thread `tid`.
+    pub fn unmap_thread_stack(&mut self, tid: usize) {
+        let removed: Vec<MapArea> = self.areas.extract_if(.., |area| area.thread_stack == Some(tid)).collect();
+        for area in removed.iter() {
+            area.unmap(&mut self.page_table);
+        }
+        if !removed.is_empty() {
+            self.flush_tlb();
+        }
+    }
+    /// Maps `frames` from `start` on, sharing them with whoever else holds them, also across
+    /// fork. `mprotect` may not raise the permission above `limit`.
+    /// Returns false if part of the range is already in use.
+    pub fn map_shared(&mut self, start: VirtPageNum, frames: &[Arc<FrameTracker>], permission: MapPermission, limit: MapPermission) -> bool {
+        let end = VirtPageNum(start.0 + frames.len());
+        if self.overlaps(start, end) {
+            return false;
+        }
+        let mut area = MapArea::new(start.into(), end.into(), Framed, permission);
+        area.share_limit = Some(limit);
+        for (vpn, frame) in VPNRange::new(start, end).into_iter().zip(frames) {
+            self.page_table.map(vpn, frame.ppn, area.pte_flags());
+            area.data_frames.insert(vpn, frame.clone());
+        }
+        self.areas.push(area);
+        true
+    }
+    /// Maps the device registers at `[base, base + size)` for user access at the same address.
+    pub fn map_mmio(&mut self, base: usize, size: usize) -> bool {
+        let (start, end) = (VirtAddr::from(base).floor(), VirtAddr::from(base + size).ceil());
+        if self.overlaps(start, end) {
+            return false;
+        }
+        let permission = MapPermission::R | MapPermission::W | MapPermission::U;
+        self.push(MapArea::new(base.into(), (base + size).into(), Identical, permission), None);
+        true
+    }
+    /// Grows or shrinks the lazy area starting at `start` so that it ends at `new_end`.
+    /// Returns false if there is no such area or it would run into another area.
+    pub fn resize_area(&mut self, start: VirtPageNum, new_end: VirtPageNum) -> bool {
+        let Some(idx) = self.areas.iter().position(|area| {
+            area.lazy && area.vpn_range.start() == start
+        }) else {
+            return false;
+        };
+        if new_end < start {
+            return false;
+        }
+        let old_end = self.areas[idx].vpn_range.end();
+        if new_end > old_end && self.overlaps(old_end, new_end) {
+            return false;
+        }
+        let removed = self.areas[idx].resize(&mut self.page_table, new_end);
+        if !removed.is_empty() {
+            self.flush_tlb();
+        }
+        true
+    }
+    /// Splits the area containing `vpn`, if any, so that no area straddles `vpn`.
+    fn split_at(&mut self, vpn: VirtPageNum) {
+        if let Some(idx) = self.areas.iter().position(|area| {
+            area.vpn_range.start() < vpn && vpn < area.vpn_range.end()
+        }) {
+            let tail = self.areas[idx].split_off(vpn);
+            self.areas.insert(idx + 1, tail);
+        }
+    }
+    /// Unmaps every page in `[start, end)`, shrinking or splitting the areas it covers.
+    pub fn munmap(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        self.split_at(start);
+        self.split_at(end);
+        // An empty area is the heap before the first brk, brk still has to find it.
+        let removed: Vec<MapArea> = self.areas.extract_if(.., |area| {
+            area.vpn_range.start() < area.vpn_range.end()
+                && start <= area.vpn_range.start()
+                && area.vpn_range.end() <= end
+        }).collect();
+        for area in removed.iter() {
+            area.unmap(&mut self.page_table);
+        }
+        if !removed.is_empty() {
+            self.flush_tlb();
+        }
+    }
+    /// Whether shared areas in `[start, end)` may be given `permission`.
+    pub fn within_share_limit(&self, start: VirtPageNum, end: VirtPageNum, permission: MapPermission) -> bool {
+        self.areas.iter().all(|area| {
+            let overlaps = area.vpn_range.start() < end && start < area.vpn_range.end();
+            !overlaps || area.share_limit.is_none_or(|limit| limit.contains(permission))
+        })
+    }
+    /// Changes the permission of `[start, end)`, splitting the areas it partially covers.
+    /// Returns false if part of the range is not mapped.
+    pub fn mprotect(&mut self, start: VirtPageNum, end: VirtPageNum, permission: MapPermission) -> bool {
+        let mut covered = 0;
+        for area in self.areas.iter() {
+            let l = area.vpn_range.start().max(start);
+            let r = area.vpn_range.end().min(end);
+            if l < r {
+                covered += r.0 - l.0;
+            }
+        }
+        if covered != end.0 - start.0 {
+            return false;
+        }
+        self.split_at(start);
+        self.split_at(end);
+        for area in self.areas.iter_mut() {
+            if start <= area.vpn_range.start() && area.vpn_range.end() <= end {
+                area.set_permission(&mut self.page_table, permission);
+            }
+        }
+        self.flush_tlb();
+        true
+    }
     pub fn translate(&self, va: VirtPageNum) -> Option<PageTableEntry> {
         self.page_table.translate(va)
     }
     pub fn token(&self) -> usize {
         self.page_table.token()
     }
+    /// Switches this hart to the address space. Only used for the kernel space, which each
+    /// hart keeps using for good.
     pub fn activate(&self) {
         let satp_val = Satp::from_bits(self.page_table.token());
         unsafe {
             satp::write(satp_val);
             asm!("sfence.vma");
         }
+        self.enter();
+    }
+    /// Records that this hart is about to run in the address space.
+    pub fn enter(&self) {
+        self.active_harts.fetch_or(1 << current_hart(), Ordering::SeqCst);
+    }
+    /// Records that this hart left the address space. The trap entry flushes the TLB, so
+    /// nothing of it stays cached once a hart is back in the kernel.
+    pub fn leave(&self) {
+        self.active_harts.fetch_and(!(1 << current_hart()), Ordering::SeqCst);
+    }
+    /// Makes every hart drop the translations it cached after PTEs were removed or lost
+    /// permissions. Frames unmapped before must not be freed until this returns.
+    fn flush_tlb(&self) {
+        remote_sfence_vma(self.active_harts.load(Ordering::SeqCst));
     }
     pub fn map_trampoline(&mut self) {
         self.page_table.map(
@@ -272,34 +574,188 @@ impl MemorySet {
         );
     }
     pub fn remove_area_with_start_vpn(&mut self, start_vpn: VirtPageNum) {
-        if let Some((idx, area)) = self
-            .areas
-            .iter_mut()
-            .enumerate()
-            .find(|(_, area)| area.vpn_range.start() == start_vpn) {
+        if let Some(idx) = self.areas.iter().position(|area| area.vpn_range.start() == start_vpn) {
+            let area = self.areas.remove(idx);
             area.unmap(&mut self.page_table);
-            self.areas.remove(idx);
+            self.flush_tlb();
         }
     }
-    pub fn from_existed_user(user_space: &MemorySet) -> MemorySet {
+    /// Builds the address space of a forked child. Private user pages are shared with the
+    /// parent read-only and only duplicated by `handle_page_fault` once either side writes to
+    /// them, shared memory stays shared.
+    pub fn from_existed_user(user_space: &mut MemorySet) -> MemorySet {
         let mut memory_set = MemorySet::new_bare();
         memory_set.map_trampoline();
         for area in user_space.areas.iter() {
-            let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
-            for vpn in area.vpn_range {
-                let src_ppn = user_space.translate(vpn).unwrap().ppn();
-                let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
-                dst_ppn.get_bytes_array().copy_from_slice(src_ppn.get_bytes_array());
+            let mut new_area = MapArea::from_another(area);
+            if area.share_limit.is_some() {
+                for (&vpn, frame) in area.data_frames.iter() {
+                    memory_set.page_table.map(vpn, frame.ppn, area.pte_flags());
+                    new_area.data_frames.insert(vpn, frame.clone());
+                }
+                memory_set.areas.push(new_area);
+                continue;
+            }
+            if !area.is_cow_shareable() {
+                memory_set.push(new_area, None);
+                for &vpn in area.data_frames.keys() {
+                    let src_ppn = user_space.page_table.translate(vpn).unwrap().ppn();
+                    let dst_ppn = memory_set.translate(vpn).unwrap().ppn();
+                    dst_ppn.get_bytes_array().copy_from_slice(src_ppn.get_bytes_array());
+                }
+                continue;
             }
+            let flags = area.pte_flags() - PTEFlags::W;
+            for (&vpn, frame) in area.data_frames.iter() {
+                user_space.page_table.remap(vpn, frame.ppn, flags);
+                memory_set.page_table.map(vpn, frame.ppn, flags);
+                new_area.data_frames.insert(vpn, frame.clone());
+            }
+            memory_set.areas.push(new_area);
         }
+        // Pages the parent could write so far are now read-only.
+        user_space.flush_tlb();
         memory_set
     }
+    /// Resolves a page fault caused by an `access` of `R`, `W` or `X` to `vpn`: either the
+    /// first touch of a lazy page, or a write to a page shared copy-on-write.
+    /// Returns false if the access is not permitted by any area, or if no frame is left to
+    /// resolve it.
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, access: MapPermission) -> bool {
+        if !self.areas.iter().any(|area| area.contains(vpn)) && !self.grow_stack(vpn, access) {
+            return false;
+        }
+        let area = self.areas.iter_mut().find(|area| area.contains(vpn)).unwrap();
+        if !area.map_permission.contains(access | MapPermission::U) || area.map_type != Framed {
+            return false;
+        }
+        match self.page_table.translate(vpn) {
+            Some(pte) if pte.is_valid() => {
+                if access == MapPermission::W && !pte.writable() {
+                    match area.copy_on_write(&mut self.page_table, vpn) {
+                        Ok(Some(_old)) => self.flush_tlb(),
+                        Ok(None) => {}
+                        Err(()) => return false
+                    }
+                }
+                // Otherwise another hart resolved the fault first, or this hart faulted on a
+                // stale translation, which the trap entry flushed.
+                true
+            }
+            _ if area.lazy => area.map_one(&mut self.page_table, vpn),
+            _ => false
+        }
+    }
+    /// Extends the user stack down to `vpn` if `vpn` lies within the stack's reserved range
+    /// and the stack permits an `access`.
+    fn grow_stack(&mut self, vpn: VirtPageNum, access: MapPermission) -> bool {
+        let limit = VirtAddr::from(USER_STACK_TOP - USER_STACK_LIMIT).floor();
+        if vpn < limit || vpn >= VirtAddr::from(USER_STACK_TOP).floor() {
+            return false;
+        }
+        let Some(stack) = self.areas.iter_mut()
+            .filter(|area| area.lazy && area.vpn_range.start() > vpn)
+            .min_by_key(|area| area.vpn_range.start()) else {
+            return false;
+        };
+        if !stack.map_permission.contains(access | MapPermission::U) {
+            return false;
+        }
+        stack.vpn_range = VPNRange::new(vpn, stack.vpn_range.end());
+        true
+    }
+    /// Whether `vpn` lies in the guard gap left unmapped between the mmap region and the user
+    /// stack's growth limit.
+    pub fn is_stack_guard(&self, vpn: VirtPageNum) -> bool {
+        let limit = VirtAddr::from(USER_STACK_TOP - USER_STACK_LIMIT).floor();
+        VirtAddr::from(MMAP_END).floor() <= vpn && vpn < limit
+    }
+    /// Makes `[start, start + len)` accessible before the kernel reads or writes it through
+    /// its physical frames, faulting in lazy pages and breaking copy-on-write sharing.
+    /// Returns false if user code could not make an `access` to all of it, in which case the
+    /// caller must not touch the range and fails with EFAULT.
+    #[must_use]
+    pub fn fault_in(&mut self, start: VirtAddr, len: usize, access: MapPermission) -> bool {
+        let Some(end) = usize::from(start).checked_add(len).filter(|&end| end <= USER_STACK_TOP) else {
+            return false;
+        };
+        VPNRange::new(start.floor(), VirtAddr::from(end).ceil()).into_iter().all(|vpn| {
+            self.is_accessible(vpn, access)
+                || (self.handle_page_fault(vpn, access) && self.is_accessible(vpn, access))
+        })
+    }
+    /// Copies `value` to user memory at `dst` page by page, since a struct may straddle two
+    /// pages whose frames are not adjacent. Returns false, having written nothing, if user
+    /// code could not write all of it.
+    #[must_use]
+    pub fn copy_to_user<T: Copy>(&mut self, dst: *mut T, value: &T) -> bool {
+        if !self.fault_in(VirtAddr::from(dst as usize), size_of::<T>(), MapPermission::W) {
+            return false;
+        }
+        let src = unsafe { core::slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+        let mut start = 0;
+        for buffer in translated_byte_buffer(self.token(), dst as *const u8, size_of::<T>()) {
+            buffer.copy_from_slice(&src[start..start + buffer.len()]);
+            start += buffer.len();
+        }
+        true
+    }
+    /// Copies a `T` from user memory at `src` page by page, like `copy_to_user`. Returns None
+    /// if user code could not read all of it. Only for types any bit pattern is valid for.
+    pub fn copy_from_user<T: Copy>(&mut self, src: *const T) -> Option<T> {
+        if !self.fault_in(VirtAddr::from(src as usize), size_of::<T>(), MapPermission::R) {
+            return None;
+        }
+        let mut value = core::mem::MaybeUninit::<T>::uninit();
+        let dst = unsafe { core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+        let mut start = 0;
+        for buffer in translated_byte_buffer(self.token(), src as *const u8, size_of::<T>()) {
+            dst[start..start + buffer.len()].copy_from_slice(buffer);
+            start += buffer.len();
+        }
+        Some(unsafe { value.assume_init() })
+    }
+    /// Copies a NUL-terminated string of at most `max_len` bytes from user memory at `src`,
+    /// faulting its pages in as it goes. Returns None if user code could not read all of it
+    /// or there is no NUL within `max_len` bytes.
+    pub fn copy_str_from_user(&mut self, src: *const u8, max_len: usize) -> Option<String> {
+        let mut string = String::new();
+        let mut va = src as usize;
+        while string.len() < max_len {
+            if (va == src as usize || VirtAddr::from(va).page_offset() == 0)
+                && !self.fault_in(VirtAddr::from(va), 1, MapPermission::R) {
+                return None;
+            }
+            let ch: u8 = *self.page_table.translate_va(va.into())?.get_mut();
+            if ch == 0 {
+                return Some(string);
+            }
+            string.push(ch as char);
+            va += 1;
+        }
+        None
+    }
+    /// Whether the PTE of `vpn` already lets user code make an `access`.
+    fn is_accessible(&self, vpn: VirtPageNum, access: MapPermission) -> bool {
+        let needed = PTEFlags::from_bits(access.bits()).unwrap() | PTEFlags::U | PTEFlags::V;
+        self.page_table.translate(vpn).is_some_and(|pte| pte.flags().contains(needed))
+    }
     pub fn recycled_data_pages(&mut self) {
+        self.flush_tlb();
         self.areas.clear();
     }
 }
 
+impl Drop for MemorySet {
+    /// Harts still running in the address space leave it on the IPI, before its frames and
+    /// page tables are freed.
+    fn drop(&mut self) {
+        if *self.active_harts.get_mut() != 0 {
+            self.flush_tlb();
+        }
+    }
+}
+
 pub fn remap_test() {
     let kernel_space = KERNEL_SPACE.exclusive_access();
     let mid_text: VirtAddr = ((stext as usize + etext as usize) / 2).into();
@@ -2,4 +2,5 @@ pub mod heap_allocator;
 pub mod address;
 pub mod page_table;
 pub mod frame_allocator;
-pub mod memory_set;
\ No newline at end of file
+pub mod memory_set;
+pub mod shared_memory;
\ No newline at end of file
@@ -1,4 +1,3 @@
-use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::bitflags;
@@ -7,6 +6,7 @@ use crate::mem::frame_allocator::{frame_alloc, FrameTracker};
 use crate::println;
 
 bitflags! {
+    #[derive(Copy, Clone)]
     pub struct PTEFlags: u8 {
         const V = 1 << 0;
         const R = 1 << 1;
@@ -72,7 +72,7 @@ impl PageTable {
                 break;
             }
             if !entry.is_valid() {
-                let frame = frame_alloc().unwrap();
+                let frame = frame_alloc()?;
                 *entry = PageTableEntry::new(frame.ppn, PTEFlags::V);
                 self.frames.push(frame);
             }
@@ -98,15 +98,29 @@ impl PageTable {
         res
     }
     pub fn map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
-        let pte = self.find_pte_create(vpn).unwrap();
+        assert!(self.try_map(vpn, ppn, flags), "No frame left for the page table of vpn {:#x}.", vpn.0);
+    }
+    /// Like `map`, but returns false if no frame is left for a page table on the way.
+    pub fn try_map(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> bool {
+        let Some(pte) = self.find_pte_create(vpn) else {
+            return false;
+        };
         assert!(!pte.is_valid(), "Trying to map vpn {:#x} twice.", vpn.0);
         *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        true
     }
     pub fn unmap(&mut self, vpn: VirtPageNum) {
         let pte = self.find_pte(vpn).unwrap();
         assert!(pte.is_valid(), "Trying to unmap non-existent vpn {}.",  vpn.0);
         *pte = PageTableEntry::empty();
     }
+    /// Points an already mapped vpn at `ppn` with new flags, e.g. when a shared frame is
+    /// downgraded to read-only or replaced by a private copy.
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
+        let pte = self.find_pte(vpn).unwrap();
+        assert!(pte.is_valid(), "Trying to remap non-existent vpn {:#x}.", vpn.0);
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+    }
     pub fn translate(&self, v: VirtPageNum) -> Option<PageTableEntry> {
         self.find_pte(v).map(|pte| { pte.clone() })
     }
@@ -145,25 +159,3 @@ pub fn translated_byte_buffer(token: usize, ptr: *const u8, len: usize) -> Vec<&
     }
     v
 }
-
-pub fn translated_str(token: usize, ptr: *const u8) -> String {
-    let page_table = PageTable::from_token(token);
-    let mut string = String::new();
-    let mut va = ptr as usize;
-    loop {
-        let ch: u8 = *(page_table.translate_va(va.into()).unwrap().get_mut());
-        if ch == 0 {
-            break;
-        } else {
-            string.push(ch as char);
-            va += 1;
-        }
-    }
-    string
-}
-
-pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
-    let page_table = PageTable::from_token(token);
-    let va = ptr as usize;
-    page_table.translate_va(va.into()).unwrap().get_mut()
-}
\ No newline at end of file
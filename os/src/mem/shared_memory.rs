@@ -0,0 +1,22 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::mem::frame_allocator::{frame_alloc, FrameTracker};
+
+/// Zeroed frames that any number of address spaces can map at once. Each mapping holds its
+/// own references, so the frames outlive the object for as long as they are mapped.
+pub struct SharedMemory {
+    frames: Vec<Arc<FrameTracker>>
+}
+
+impl SharedMemory {
+    /// Returns None if memory runs out.
+    pub fn new(pages: usize) -> Option<Self> {
+        let frames = (0..pages)
+            .map(|_| frame_alloc().map(Arc::new))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { frames })
+    }
+    pub fn frames(&self) -> &[Arc<FrameTracker>] {
+        &self.frames
+    }
+}
@@ -0,0 +1,31 @@
+use alloc::sync::Arc;
+use crate::sync::mutex::Mutex;
+use crate::sync::spin::SpinLock;
+use crate::task::block_current_and_run_next;
+use crate::task::processor::current_task;
+use crate::task::wait_queue::WaitQueue;
+
+/// A condition variable. Signals that nobody waits for are lost.
+pub struct Condvar {
+    wait_queue: SpinLock<WaitQueue>
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self {
+            wait_queue: SpinLock::new(WaitQueue::new())
+        }
+    }
+    /// Wakes the thread that has waited longest, if any.
+    pub fn signal(&self) {
+        self.wait_queue.exclusive_access().wake_one();
+    }
+    /// Releases `mutex`, blocks until signalled and takes `mutex` again. The caller is queued
+    /// before the mutex is released, so a signal sent in between is not missed.
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) {
+        self.wait_queue.exclusive_access().push(current_task().unwrap());
+        mutex.unlock();
+        block_current_and_run_next();
+        mutex.lock();
+    }
+}
@@ -0,0 +1,91 @@
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU32, Ordering};
+use lazy_static::lazy_static;
+use crate::mem::address::PhysAddr;
+use crate::sync::spin::SpinLock;
+use crate::task::{block_current_and_run_next, wakeup_task};
+use crate::task::processor::current_task;
+use crate::task::task::TaskControlBlock;
+use crate::timer::sleep_queue::{add_sleeping_task, cancel_sleep};
+
+/// A task waiting on a futex. `timed` if the timer may wake it as well.
+struct FutexWaiter {
+    task: Arc<TaskControlBlock>,
+    timed: bool
+}
+
+lazy_static! {
+    /// Tasks waiting on a futex word, keyed by its physical address so that a word mapped
+    /// into several address spaces is the same futex in all of them.
+    static ref FUTEX_QUEUES: SpinLock<BTreeMap<usize, VecDeque<FutexWaiter>>> =
+        SpinLock::new(BTreeMap::new());
+}
+
+/// How `futex_wait` returned.
+pub enum FutexWait {
+    Woken,
+    TimedOut,
+    /// The word no longer held the expected value, so the task did not block.
+    ValueChanged
+}
+
+/// Blocks the current task on the futex at `pa`, if the word there still holds `val`, until
+/// `futex_wake` or, if there is a `deadline`, the timer wakes it.
+pub fn futex_wait(pa: usize, val: u32, deadline: Option<usize>) -> FutexWait {
+    let task = current_task().unwrap();
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    // Compare and enqueue in one critical section, so that a waker that changes the word on
+    // another hart either makes the compare fail or finds this task queued.
+    let word = PhysAddr::from(pa).get_mut::<AtomicU32>();
+    if word.load(Ordering::SeqCst) != val {
+        return FutexWait::ValueChanged;
+    }
+    // The timer goes first, so that `futex_wake` can tell from a missing timer that the
+    // deadline has passed.
+    if let Some(deadline) = deadline {
+        add_sleeping_task(deadline, task.clone());
+    }
+    let waiter = FutexWaiter { task: task.clone(), timed: deadline.is_some() };
+    queues.entry(pa).or_default().push_back(waiter);
+    drop(queues);
+    block_current_and_run_next();
+    // `futex_wake` dequeues the tasks it wakes, so a task still queued was woken by the timer.
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    let Some(queue) = queues.get_mut(&pa) else {
+        return FutexWait::Woken;
+    };
+    let Some(index) = queue.iter().position(|waiter| Arc::ptr_eq(&waiter.task, &task)) else {
+        return FutexWait::Woken;
+    };
+    queue.remove(index);
+    if queue.is_empty() {
+        queues.remove(&pa);
+    }
+    FutexWait::TimedOut
+}
+
+/// Wakes up to `count` tasks waiting on the futex at `pa` and returns how many it woke.
+/// Tasks that timed out but have not dequeued themselves yet are left for `futex_wait`.
+pub fn futex_wake(pa: usize, count: usize) -> usize {
+    let mut queues = FUTEX_QUEUES.exclusive_access();
+    let Some(queue) = queues.get_mut(&pa) else {
+        return 0;
+    };
+    let mut woken = 0;
+    queue.retain(|waiter| {
+        if waiter.task.is_exited() {
+            return false;
+        }
+        if woken == count || waiter.timed && !cancel_sleep(&waiter.task) {
+            return true;
+        }
+        wakeup_task(waiter.task.clone());
+        woken += 1;
+        false
+    });
+    if queue.is_empty() {
+        queues.remove(&pa);
+    }
+    woken
+}
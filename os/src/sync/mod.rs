@@ -1 +1,5 @@
-pub mod up;
\ No newline at end of file
+pub mod condvar;
+pub mod futex;
+pub mod mutex;
+pub mod semaphore;
+pub mod spin;
\ No newline at end of file
@@ -0,0 +1,82 @@
+use crate::sync::spin::SpinLock;
+use crate::task::{block_current_and_run_next, suspend_current_and_run_next};
+use crate::task::processor::current_task;
+use crate::task::wait_queue::WaitQueue;
+
+/// A lock shared by the threads of a process.
+pub trait Mutex: Sync + Send {
+    fn lock(&self);
+    /// Releases the lock. Unlocking a mutex that is not locked does nothing.
+    fn unlock(&self);
+}
+
+/// Yields until the lock is free. Cheap when it is held only briefly.
+pub struct MutexSpin {
+    locked: SpinLock<bool>
+}
+
+impl MutexSpin {
+    pub fn new() -> Self {
+        Self {
+            locked: SpinLock::new(false)
+        }
+    }
+}
+
+impl Mutex for MutexSpin {
+    fn lock(&self) {
+        loop {
+            let mut locked = self.locked.exclusive_access();
+            if !*locked {
+                *locked = true;
+                return;
+            }
+            drop(locked);
+            suspend_current_and_run_next();
+        }
+    }
+    fn unlock(&self) {
+        *self.locked.exclusive_access() = false;
+    }
+}
+
+/// Blocks until the lock is free. The lock is handed straight to the thread that has waited
+/// longest, so it cannot be taken from under it between the unlock and its wakeup.
+pub struct MutexBlocking {
+    inner: SpinLock<MutexBlockingInner>
+}
+
+struct MutexBlockingInner {
+    locked: bool,
+    wait_queue: WaitQueue
+}
+
+impl MutexBlocking {
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(MutexBlockingInner {
+                locked: false,
+                wait_queue: WaitQueue::new()
+            })
+        }
+    }
+}
+
+impl Mutex for MutexBlocking {
+    fn lock(&self) {
+        let mut inner = self.inner.exclusive_access();
+        if !inner.locked {
+            inner.locked = true;
+            return;
+        }
+        inner.wait_queue.push(current_task().unwrap());
+        drop(inner);
+        block_current_and_run_next();
+    }
+    fn unlock(&self) {
+        let mut inner = self.inner.exclusive_access();
+        if !inner.wait_queue.wake_one() {
+            inner.locked = false;
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::sync::spin::SpinLock;
+use crate::task::block_current_and_run_next;
+use crate::task::processor::current_task;
+use crate::task::wait_queue::WaitQueue;
+
+/// A counting semaphore. Like `MutexBlocking`, `up` hands its unit straight to the thread
+/// that has waited longest instead of adding it to the count.
+pub struct Semaphore {
+    inner: SpinLock<SemaphoreInner>
+}
+
+struct SemaphoreInner {
+    count: usize,
+    wait_queue: WaitQueue
+}
+
+impl Semaphore {
+    pub fn new(count: usize) -> Self {
+        Self {
+            inner: SpinLock::new(SemaphoreInner {
+                count,
+                wait_queue: WaitQueue::new()
+            })
+        }
+    }
+    pub fn up(&self) {
+        let mut inner = self.inner.exclusive_access();
+        if !inner.wait_queue.wake_one() {
+            inner.count += 1;
+        }
+    }
+    pub fn down(&self) {
+        let mut inner = self.inner.exclusive_access();
+        if inner.count > 0 {
+            inner.count -= 1;
+            return;
+        }
+        inner.wait_queue.push(current_task().unwrap());
+        drop(inner);
+        block_current_and_run_next();
+    }
+}
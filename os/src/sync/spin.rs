@@ -0,0 +1,66 @@
+use core::cell::UnsafeCell;
+use core::hint::spin_loop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use crate::task::processor::current_hart;
+use crate::trap::ipi::handle_ipi;
+
+const NO_HART: usize = usize::MAX;
+
+/// A lock that busy-waits, shared by all harts. Kernel code runs with interrupts masked, so
+/// the holder is never interrupted by something that takes the lock again. A hart taking a
+/// lock it already holds would wait forever, which is reported as a panic instead.
+pub struct SpinLock<T> {
+    locked: AtomicBool,
+    /// Hart holding the lock, `NO_HART` while it is free.
+    owner: AtomicUsize,
+    data: UnsafeCell<T>
+}
+
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+unsafe impl<T: Send> Send for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            owner: AtomicUsize::new(NO_HART),
+            data: UnsafeCell::new(value)
+        }
+    }
+    pub fn exclusive_access(&self) -> SpinLockGuard<'_, T> {
+        let hart = current_hart();
+        while self.locked.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            assert_ne!(self.owner.load(Ordering::Relaxed), hart, "lock taken twice by hart {}", hart);
+            // The holder may be waiting for this hart to answer an IPI.
+            handle_ipi();
+            spin_loop();
+        }
+        self.owner.store(hart, Ordering::Relaxed);
+        SpinLockGuard { lock: self }
+    }
+}
+
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.owner.store(NO_HART, Ordering::Relaxed);
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,272 @@
+use alloc::sync::Arc;
+use crate::cap::{CapObject, CapRights, Capability};
+use crate::config::{MMAP_END, USER_MMIO};
+use crate::drivers::plic::{ack_irq, route_irq, MAX_IRQ};
+use crate::ipc::NOTIFICATION_BADGE;
+use crate::mem::address::{VirtAddr, PAGE_SIZE};
+use crate::mem::frame_allocator::{frame_alloc, FrameTracker};
+use crate::mem::memory_set::MapPermission;
+use crate::mem::shared_memory::SharedMemory;
+use crate::syscall::errno::{EBADF, EBUSY, EEXIST, EINVAL, ENOMEM, ENOSPC, EPERM};
+use crate::syscall::mem::{prot_to_permission, PROT_WRITE};
+use crate::task::processor::current_process;
+
+/// Looks up the capability in `slot` of the current process and checks that it has `rights`.
+pub fn lookup_cap(slot: usize, rights: CapRights) -> Result<Capability, isize> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let cap = inner.cspace.get(slot).ok_or(EBADF)?;
+    if !cap.rights.contains(rights) {
+        return Err(EPERM);
+    }
+    Ok(cap.clone())
+}
+
+/// Puts `cap` into a free slot of the current process and returns the slot.
+pub fn insert_cap(cap: Capability) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.cspace.insert(cap) {
+        Some(slot) => slot as isize,
+        None => -ENOSPC
+    }
+}
+
+/// Derives a copy of `src` into the empty slot `dest` with at most `rights`.
+/// Only an endpoint or notification capability that has no badge yet can be given one.
+fn derive(src: usize, dest: usize, rights: usize, badge: usize) -> isize {
+    let Some(rights) = CapRights::from_bits(rights) else {
+        return -EINVAL;
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let Some(cap) = inner.cspace.get(src) else {
+        return -EBADF;
+    };
+    let badge = match (badge, &cap.object) {
+        (0, _) => cap.badge,
+        (badge, CapObject::Endpoint(_)) if cap.badge == 0 && badge < NOTIFICATION_BADGE as usize => badge,
+        (badge, CapObject::Notification(_)) if cap.badge == 0 && (badge as isize) > 0 => badge,
+        _ => return -EINVAL
+    };
+    let derived = cap.derive(rights, badge);
+    if inner.cspace.insert_at(dest, derived) { 0 } else { -EEXIST }
+}
+
+pub fn sys_cap_copy(src: usize, dest: usize) -> isize {
+    derive(src, dest, CapRights::all().bits(), 0)
+}
+
+pub fn sys_cap_mint(src: usize, dest: usize, rights: usize, badge: usize) -> isize {
+    derive(src, dest, rights, badge)
+}
+
+pub fn sys_cap_move(src: usize, dest: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if !inner.cspace.is_free(dest) {
+        return -EEXIST;
+    }
+    match inner.cspace.remove(src) {
+        Some(cap) => {
+            inner.cspace.insert_at(dest, cap);
+            0
+        }
+        None => -EBADF
+    }
+}
+
+pub fn sys_cap_delete(slot: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    match inner.cspace.remove(slot) {
+        Some(_) => 0,
+        None => -EBADF
+    }
+}
+
+/// Deletes every capability derived from the one in `slot`, in any process.
+pub fn sys_cap_revoke(slot: usize) -> isize {
+    match lookup_cap(slot, CapRights::empty()) {
+        Ok(cap) => {
+            cap.revoke();
+            0
+        }
+        Err(err) => -err
+    }
+}
+
+/// Returns the type of the capability in `slot`: 1 endpoint, 2 frame, 3 address space,
+/// 4 IRQ control, 5 IRQ handler, 6 notification and 7 shared memory.
+pub fn sys_cap_identify(slot: usize) -> isize {
+    match lookup_cap(slot, CapRights::empty()) {
+        Ok(cap) => match cap.object {
+            CapObject::Endpoint(_) => 1,
+            CapObject::Frame(_) => 2,
+            CapObject::AddressSpace(_) => 3,
+            CapObject::IrqControl => 4,
+            CapObject::IrqHandler(_) => 5,
+            CapObject::Notification(_) => 6,
+            CapObject::SharedMemory(_) => 7
+        },
+        Err(err) => -err
+    }
+}
+
+/// Allocates a zeroed physical frame and returns the slot of its capability.
+pub fn sys_frame_alloc() -> isize {
+    match frame_alloc() {
+        Some(frame) => insert_cap(Capability::new(CapObject::Frame(Arc::new(frame)))),
+        None => -ENOMEM
+    }
+}
+
+/// Maps `frames` at `addr` of the address space in `aspace_slot`, or wherever there is room
+/// if `addr` is 0, and returns the address. `prot` takes `PROT_*` bits and may not exceed
+/// `rights`, the rights of the capability the frames were found through, not even later
+/// through mprotect.
+fn map_frames(aspace_slot: usize, addr: usize, frames: &[Arc<FrameTracker>], prot: usize, rights: CapRights) -> Result<usize, isize> {
+    let permission = prot_to_permission(prot).ok_or(EINVAL)?;
+    if !addr.is_multiple_of(PAGE_SIZE) || addr.checked_add(frames.len() * PAGE_SIZE).is_none_or(|end| end > MMAP_END) {
+        return Err(EINVAL);
+    }
+    if !rights.contains(CapRights::READ) || (prot & PROT_WRITE != 0 && !rights.contains(CapRights::WRITE)) {
+        return Err(EPERM);
+    }
+    let mut limit = MapPermission::U | MapPermission::R | MapPermission::X;
+    if rights.contains(CapRights::WRITE) {
+        limit |= MapPermission::W;
+    }
+    let process = match lookup_cap(aspace_slot, CapRights::WRITE)? {
+        Capability { object: CapObject::AddressSpace(process), .. } => process,
+        _ => return Err(EINVAL)
+    };
+    let process = process.upgrade().ok_or(EBADF)?;
+    let mut inner = process.inner_exclusive_access();
+    let start = if addr == 0 {
+        inner.memory_set.find_free_area(frames.len()).ok_or(ENOMEM)?
+    } else {
+        VirtAddr::from(addr).floor()
+    };
+    if !inner.memory_set.map_shared(start, frames, permission, limit) {
+        return Err(EEXIST);
+    }
+    Ok(VirtAddr::from(start).into())
+}
+
+/// Maps the frame in `frame_slot` at `addr` of the address space in `aspace_slot`.
+pub fn sys_frame_map(aspace_slot: usize, frame_slot: usize, addr: usize, prot: usize) -> isize {
+    let (frame, rights) = match lookup_cap(frame_slot, CapRights::empty()) {
+        Ok(Capability { object: CapObject::Frame(frame), rights, .. }) => (frame, rights),
+        Ok(_) => return -EINVAL,
+        Err(err) => return -err
+    };
+    match map_frames(aspace_slot, addr, &[frame], prot, rights) {
+        Ok(_) => 0,
+        Err(err) => -err
+    }
+}
+
+/// Allocates `len` bytes of zeroed shared memory and returns the slot of its capability.
+pub fn sys_shm_create(len: usize) -> isize {
+    if len == 0 {
+        return -EINVAL;
+    }
+    match SharedMemory::new(len.div_ceil(PAGE_SIZE)) {
+        Some(shm) => insert_cap(Capability::new(CapObject::SharedMemory(Arc::new(shm)))),
+        None => -ENOMEM
+    }
+}
+
+/// Maps all of the shared memory in `shm_slot` into the address space in `aspace_slot`, at
+/// `addr` or anywhere if `addr` is 0, and returns the address.
+pub fn sys_shm_map(aspace_slot: usize, shm_slot: usize, addr: usize, prot: usize) -> isize {
+    let (shm, rights) = match lookup_cap(shm_slot, CapRights::empty()) {
+        Ok(Capability { object: CapObject::SharedMemory(shm), rights, .. }) => (shm, rights),
+        Ok(_) => return -EINVAL,
+        Err(err) => return -err
+    };
+    match map_frames(aspace_slot, addr, shm.frames(), prot, rights) {
+        Ok(addr) => addr as isize,
+        Err(err) => -err
+    }
+}
+
+/// Creates a handler capability for interrupt source `irq` from the IRQ control capability
+/// in `control_slot` and returns its slot.
+pub fn sys_irq_handler_get(control_slot: usize, irq: usize) -> isize {
+    match lookup_cap(control_slot, CapRights::empty()) {
+        Ok(Capability { object: CapObject::IrqControl, .. }) => {}
+        Ok(_) => return -EINVAL,
+        Err(err) => return -err
+    }
+    if irq == 0 || irq >= MAX_IRQ {
+        return -EINVAL;
+    }
+    let process = current_process();
+    let held = process.inner_exclusive_access().cspace.iter().any(|cap| {
+        matches!(cap.object, CapObject::IrqHandler(handled) if handled == irq)
+    });
+    if held {
+        return -EEXIST;
+    }
+    insert_cap(Capability::new(CapObject::IrqHandler(irq)))
+}
+
+/// Looks up the IRQ handler capability in `slot` and returns its interrupt source.
+fn irq_handler(slot: usize) -> Result<usize, isize> {
+    match lookup_cap(slot, CapRights::empty())? {
+        Capability { object: CapObject::IrqHandler(irq), .. } => Ok(irq),
+        _ => Err(EINVAL)
+    }
+}
+
+/// Delivers the interrupts of the handler in `handler_slot` to the notification in
+/// `notification_slot`, taking them over from any kernel driver. Each interrupt signals the
+/// badge of the notification capability, or bit 0 if it has none. The kernel driver gets
+/// the source back once the notification is gone.
+pub fn sys_irq_handler_set_notification(handler_slot: usize, notification_slot: usize) -> isize {
+    let irq = match irq_handler(handler_slot) {
+        Ok(irq) => irq,
+        Err(err) => return -err
+    };
+    let (notification, badge) = match lookup_cap(notification_slot, CapRights::WRITE) {
+        Ok(Capability { object: CapObject::Notification(notification), badge, .. }) => (notification, badge),
+        Ok(_) => return -EINVAL,
+        Err(err) => return -err
+    };
+    let bits = if badge == 0 { 1 } else { badge };
+    if route_irq(irq, &notification, bits) { 0 } else { -EBUSY }
+}
+
+/// Unmasks the source of the handler in `slot` after the driver has served the device.
+pub fn sys_irq_ack(slot: usize) -> isize {
+    match irq_handler(slot) {
+        Ok(irq) => if ack_irq(irq) { 0 } else { -EINVAL },
+        Err(err) => -err
+    }
+}
+
+/// Maps the device registers starting at physical address `base`, one of `USER_MMIO`, into
+/// the address space in `aspace_slot` at the same address, which is returned. Devices are
+/// handed out by the IRQ control capability in `control_slot`.
+pub fn sys_mmio_map(control_slot: usize, aspace_slot: usize, base: usize) -> isize {
+    match lookup_cap(control_slot, CapRights::empty()) {
+        Ok(Capability { object: CapObject::IrqControl, .. }) => {}
+        Ok(_) => return -EINVAL,
+        Err(err) => return -err
+    }
+    let Some(&(base, size)) = USER_MMIO.iter().find(|&&(start, _)| start == base) else {
+        return -EINVAL;
+    };
+    let process = match lookup_cap(aspace_slot, CapRights::WRITE) {
+        Ok(Capability { object: CapObject::AddressSpace(process), .. }) => process,
+        Ok(_) => return -EINVAL,
+        Err(err) => return -err
+    };
+    let Some(process) = process.upgrade() else {
+        return -EBADF;
+    };
+    let mut inner = process.inner_exclusive_access();
+    if inner.memory_set.map_mmio(base, size) { base as isize } else { -EEXIST }
+}
@@ -0,0 +1,18 @@
+//! Error numbers returned (negated) by syscalls, following Linux.
+
+pub const EPERM: isize = 1;
+pub const ESRCH: isize = 3;
+pub const EBADF: isize = 9;
+pub const EAGAIN: isize = 11;
+pub const ENOMEM: isize = 12;
+pub const EACCES: isize = 13;
+pub const EFAULT: isize = 14;
+pub const EBUSY: isize = 16;
+pub const EEXIST: isize = 17;
+pub const EINVAL: isize = 22;
+pub const ENOTTY: isize = 25;
+pub const ENOSPC: isize = 28;
+pub const EPIPE: isize = 32;
+pub const EDEADLK: isize = 35;
+pub const ENOSYS: isize = 38;
+pub const ETIMEDOUT: isize = 110;
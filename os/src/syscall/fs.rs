@@ -1,21 +1,56 @@
-use crate::io::stdout::getchar;
+use crate::io::tty::TTY;
 use crate::mem::page_table::translated_byte_buffer;
 use crate::print;
-use crate::task::processor::current_user_token;
+use crate::mem::address::VirtAddr;
+use crate::mem::memory_set::MapPermission;
+use crate::syscall::errno::{EFAULT, EINVAL, ENOTTY};
+use crate::task::block_current_and_run_next;
+use crate::task::processor::{current_process, current_task, current_user_token};
 
 const FD_STDIN: usize = 0;
 const FD_STDOUT: usize = 1;
+/// Most bytes one read takes from the console, which bounds the kernel buffer it needs.
+const READ_MAX: usize = 4096;
+
+/// Simplified termios requests: only the local mode flags are transferred, as a `usize`.
+const TCGETS: usize = 0x5401;
+const TCSETS: usize = 0x5402;
+const TIOCSPGRP: usize = 0x5410;
 
 pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
     match fd {
         FD_STDIN => {
-            assert_eq!(len, 1, "Only support len = 1 in sys_read");
-            let c = getchar();
-            let mut buffers = translated_byte_buffer(current_user_token(), buf, len);
-            unsafe {
-                buffers[0].as_mut_ptr().write_volatile(c);
+            let len = len.min(READ_MAX);
+            // Fail a bad buffer before waiting for input that would then be lost.
+            if !current_process().inner_exclusive_access()
+                .memory_set.fault_in(VirtAddr::from(buf as usize), len, MapPermission::W) {
+                return -EFAULT;
+            }
+            let task = current_task().unwrap();
+            let mut data = alloc::vec![0u8; len];
+            let read_len = loop {
+                let mut tty = TTY.exclusive_access();
+                if let Some(read_len) = tty.read(&mut data) {
+                    break read_len;
+                }
+                tty.wait(task.clone());
+                drop(tty);
+                block_current_and_run_next();
+            };
+            // Another thread may have unmapped the buffer while this one blocked. Holding the
+            // lock keeps it mapped until the data is copied.
+            let process = current_process();
+            let mut inner = process.inner_exclusive_access();
+            if !inner.memory_set.fault_in(VirtAddr::from(buf as usize), read_len, MapPermission::W) {
+                return -EFAULT;
+            }
+            let buffers = translated_byte_buffer(inner.memory_set.token(), buf, read_len);
+            let mut start = 0;
+            for buffer in buffers {
+                buffer.copy_from_slice(&data[start..start + buffer.len()]);
+                start += buffer.len();
             }
-            1
+            read_len as isize
         }
         _ => {
             panic!("Unsupported fd = {} in sys_read!", fd)
@@ -26,6 +61,10 @@ pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
     match fd {
         FD_STDOUT => {
+            if !current_process().inner_exclusive_access()
+                .memory_set.fault_in(VirtAddr::from(buf as usize), len, MapPermission::R) {
+                return -EFAULT;
+            }
             let buffers = translated_byte_buffer(current_user_token(), buf, len);
             for buffer in buffers {
                 print!("{}", core::str::from_utf8(buffer).unwrap());
@@ -36,4 +75,31 @@ pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
             panic!("Unsupported fd in sys_write!");
         }
     }
-}
\ No newline at end of file
+}
+/// Gets or sets the console's line discipline mode, or sets the process Ctrl-C interrupts, see
+/// `io::tty`. Unlike in Linux, `TIOCSPGRP` takes the pid itself rather than a pointer to it.
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    if fd != FD_STDIN && fd != FD_STDOUT {
+        return -ENOTTY;
+    }
+    match request {
+        TCGETS => {
+            let process = current_process();
+            let mut inner = process.inner_exclusive_access();
+            let mode = TTY.exclusive_access().mode();
+            if !inner.memory_set.copy_to_user(arg as *mut usize, &mode) {
+                return -EFAULT;
+            }
+            0
+        }
+        TCSETS => {
+            TTY.exclusive_access().set_mode(arg);
+            0
+        }
+        TIOCSPGRP => {
+            TTY.exclusive_access().set_foreground(arg);
+            0
+        }
+        _ => -EINVAL
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::sync::Arc;
+use crate::cap::{CapObject, CapRights, Capability};
+use crate::ipc::endpoint::Endpoint;
+use crate::ipc::notification::Notification;
+use crate::ipc::{reply, IpcMessage, NO_CAP};
+use crate::syscall::cap::{insert_cap, lookup_cap};
+use crate::syscall::errno::{EBUSY, EINVAL};
+use crate::task::processor::current_task;
+
+/// Looks up an endpoint capability with `rights`, returning the endpoint and its badge.
+fn endpoint(slot: usize, rights: CapRights) -> Result<(Arc<Endpoint>, usize), isize> {
+    match lookup_cap(slot, rights)? {
+        Capability { object: CapObject::Endpoint(endpoint), badge, .. } => Ok((endpoint, badge)),
+        _ => Err(EINVAL)
+    }
+}
+
+/// Looks up a notification capability with `rights`, returning the notification and its badge.
+fn notification(slot: usize, rights: CapRights) -> Result<(Arc<Notification>, usize), isize> {
+    match lookup_cap(slot, rights)? {
+        Capability { object: CapObject::Notification(notification), badge, .. } => Ok((notification, badge)),
+        _ => Err(EINVAL)
+    }
+}
+
+/// Looks up the capability to transfer with a message, which the receiver gets a copy of.
+fn transferred_cap(slot: usize) -> Result<Option<Capability>, isize> {
+    if slot == NO_CAP {
+        return Ok(None);
+    }
+    let cap = lookup_cap(slot, CapRights::empty())?;
+    Ok(Some(cap.derive(cap.rights, cap.badge)))
+}
+
+/// Creates an endpoint and returns the slot of its capability.
+pub fn sys_endpoint_create() -> isize {
+    insert_cap(Capability::new(CapObject::Endpoint(Arc::new(Endpoint::new()))))
+}
+
+/// Sends a message and a copy of the capability in `cap_slot` unless it is `NO_CAP`.
+pub fn sys_send(slot: usize, msg: IpcMessage, cap_slot: usize) -> isize {
+    let (endpoint, badge) = match endpoint(slot, CapRights::WRITE) {
+        Ok(endpoint) => endpoint,
+        Err(err) => return -err
+    };
+    match transferred_cap(cap_slot) {
+        Ok(cap) => endpoint.send(&current_task().unwrap(), msg, badge, false, cap),
+        Err(err) => -err
+    }
+}
+
+/// Receives a message and returns the badge of the capability it was sent through. The slot
+/// a transferred capability was put into is returned in a5.
+pub fn sys_recv(slot: usize) -> isize {
+    match endpoint(slot, CapRights::READ) {
+        Ok((endpoint, _)) => endpoint.recv(&current_task().unwrap()),
+        Err(err) => -err
+    }
+}
+
+/// Sends a message like `sys_send` and waits for the receiver to reply.
+pub fn sys_call(slot: usize, msg: IpcMessage, cap_slot: usize) -> isize {
+    let (endpoint, badge) = match endpoint(slot, CapRights::WRITE) {
+        Ok(endpoint) => endpoint,
+        Err(err) => return -err
+    };
+    match transferred_cap(cap_slot) {
+        Ok(cap) => endpoint.send(&current_task().unwrap(), msg, badge, true, cap),
+        Err(err) => -err
+    }
+}
+
+pub fn sys_reply(msg: IpcMessage, cap_slot: usize) -> isize {
+    let cap = match transferred_cap(cap_slot) {
+        Ok(cap) => cap,
+        Err(err) => return -err
+    };
+    if reply(&current_task().unwrap(), msg, cap) {
+        0
+    } else {
+        -EINVAL
+    }
+}
+
+/// Creates a notification and returns the slot of its capability.
+pub fn sys_notification_create() -> isize {
+    insert_cap(Capability::new(CapObject::Notification(Arc::new(Notification::new()))))
+}
+
+/// ORs `bits` and the badge of the capability into the notification word, without blocking.
+/// The top bit is not usable, since `sys_wait` returns the word.
+pub fn sys_signal(slot: usize, bits: usize) -> isize {
+    match notification(slot, CapRights::WRITE) {
+        Ok((notification, badge)) => {
+            if bits | badge == 0 || ((bits | badge) as isize) < 0 {
+                return -EINVAL;
+            }
+            notification.signal(bits | badge);
+            0
+        }
+        Err(err) => -err
+    }
+}
+
+/// Blocks until the notification word is not 0, then returns and clears it.
+pub fn sys_wait(slot: usize) -> isize {
+    match notification(slot, CapRights::READ) {
+        Ok((notification, _)) => notification.wait(&current_task().unwrap()),
+        Err(err) => -err
+    }
+}
+
+/// Returns and clears the notification word, which may be 0.
+pub fn sys_poll(slot: usize) -> isize {
+    match notification(slot, CapRights::READ) {
+        Ok((notification, _)) => notification.poll() as isize,
+        Err(err) => -err
+    }
+}
+
+/// Binds a notification to an endpoint, so that receiving on the endpoint also returns when
+/// the notification is signalled. Each side can be bound only once.
+pub fn sys_notification_bind(endpoint_slot: usize, notification_slot: usize) -> isize {
+    let (endpoint, _) = match endpoint(endpoint_slot, CapRights::READ) {
+        Ok(endpoint) => endpoint,
+        Err(err) => return -err
+    };
+    let (notification, _) = match notification(notification_slot, CapRights::READ) {
+        Ok(notification) => notification,
+        Err(err) => return -err
+    };
+    if endpoint.bind_notification(notification) { 0 } else { -EBUSY }
+}
@@ -0,0 +1,100 @@
+use crate::config::MMAP_END;
+use crate::mem::address::{VirtAddr, VirtPageNum, PAGE_SIZE};
+use crate::mem::memory_set::MapPermission;
+use crate::syscall::errno::{EACCES, EEXIST, EINVAL, ENOMEM};
+use crate::task::processor::current_process;
+
+const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+const PROT_EXEC: usize = 1 << 2;
+
+/// Converts `PROT_*` bits into the permission of a user area.
+/// Mappings must be readable or executable, and writable pages must also be readable.
+pub fn prot_to_permission(prot: usize) -> Option<MapPermission> {
+    if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 || prot & (PROT_READ | PROT_EXEC) == 0 {
+        return None;
+    }
+    if prot & PROT_WRITE != 0 && prot & PROT_READ == 0 {
+        return None;
+    }
+    let mut permission = MapPermission::U;
+    if prot & PROT_READ != 0 { permission |= MapPermission::R; }
+    if prot & PROT_WRITE != 0 { permission |= MapPermission::W; }
+    if prot & PROT_EXEC != 0 { permission |= MapPermission::X; }
+    Some(permission)
+}
+
+/// Checks that `[addr, addr + len)` is a non-empty, page aligned range of user space.
+fn check_range(addr: usize, len: usize) -> bool {
+    addr.is_multiple_of(PAGE_SIZE) && len != 0 && addr.checked_add(len).is_some_and(|end| end <= MMAP_END)
+}
+
+/// Maps `len` bytes of anonymous, zero-filled memory at `addr`, or wherever there is room
+/// if `addr` is 0. Returns the start of the mapping.
+pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> isize {
+    let Some(permission) = prot_to_permission(prot) else {
+        return -EINVAL;
+    };
+    if !addr.is_multiple_of(PAGE_SIZE) || len == 0 {
+        return -EINVAL;
+    }
+    let pages = len.div_ceil(PAGE_SIZE);
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let start = if addr == 0 {
+        match inner.memory_set.find_free_area(pages) {
+            Some(start) => start,
+            None => return -ENOMEM
+        }
+    } else if check_range(addr, pages * PAGE_SIZE) {
+        VirtAddr::from(addr).floor()
+    } else {
+        return -EINVAL;
+    };
+    let end = VirtPageNum(start.0 + pages);
+    if !inner.memory_set.mmap(start, end, permission) {
+        return -EEXIST;
+    }
+    usize::from(VirtAddr::from(start)) as isize
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    if !check_range(addr, len) {
+        return -EINVAL;
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.memory_set.munmap(VirtAddr::from(addr).floor(), VirtAddr::from(addr + len).ceil());
+    0
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    let Some(permission) = prot_to_permission(prot) else {
+        return -EINVAL;
+    };
+    if !check_range(addr, len) {
+        return -EINVAL;
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let start = VirtAddr::from(addr).floor();
+    let end = VirtAddr::from(addr + len).ceil();
+    if !inner.memory_set.within_share_limit(start, end, permission) {
+        return -EACCES;
+    }
+    if inner.memory_set.mprotect(start, end, permission) {
+        0
+    } else {
+        -ENOMEM
+    }
+}
+
+/// Sets the program break to `addr` and returns the new break, or returns the current break
+/// if `addr` is 0 or the heap cannot be resized.
+pub fn sys_brk(addr: usize) -> isize {
+    let process = current_process();
+    if addr != 0 {
+        process.change_program_brk(addr);
+    }
+    process.inner_exclusive_access().program_brk as isize
+}
@@ -1,25 +1,100 @@
+mod cap;
+pub mod errno;
 mod fs;
+mod ipc;
+mod mem;
 mod process;
+mod signal;
+mod sync;
+mod thread;
 
 use fs::sys_write;
 use process::sys_exit;
-use crate::syscall::fs::sys_read;
-use crate::syscall::process::{sys_exec, sys_fork, sys_get_time, sys_getpid, sys_shutdown, sys_waitpid, sys_yield};
+use crate::syscall::fs::{sys_ioctl, sys_read};
+use crate::syscall::cap::{sys_cap_copy, sys_cap_delete, sys_cap_identify, sys_cap_mint, sys_cap_move, sys_cap_revoke, sys_frame_alloc, sys_frame_map, sys_irq_ack, sys_shm_create, sys_shm_map, sys_irq_handler_get, sys_irq_handler_set_notification, sys_mmio_map};
+use crate::syscall::ipc::{sys_call, sys_endpoint_create, sys_notification_bind, sys_notification_create, sys_poll, sys_recv, sys_reply, sys_send, sys_signal, sys_wait};
+use crate::syscall::mem::{sys_brk, sys_mmap, sys_mprotect, sys_munmap};
+use crate::syscall::process::{sys_clock_gettime, sys_exec, sys_fork, sys_get_idle_time, sys_get_time, sys_getpid, sys_getrusage, sys_get_sched_stat, sys_nanosleep, sys_nice, sys_sched_setaffinity, sys_set_priority, sys_shutdown, sys_times, sys_waitpid, sys_yield};
+use crate::syscall::signal::{sys_kill, sys_sigaction, sys_sigprocmask, sys_sigreturn};
+use crate::syscall::sync::{sys_condvar_create, sys_condvar_signal, sys_condvar_wait, sys_futex, sys_mutex_create, sys_mutex_lock, sys_mutex_unlock, sys_semaphore_create, sys_semaphore_down, sys_semaphore_up};
+use crate::syscall::thread::{sys_gettid, sys_thread_create, sys_waittid};
+use crate::task::processor::SchedStat;
+use crate::task::signal::SignalAction;
+use crate::task::usage::{Rusage, Tms};
+use crate::timer::TimeSpec;
 
 const SYSCALL_OPEN: usize = 17;
+const SYSCALL_IOCTL: usize = 29;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_GETRUSAGE: usize = 165;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_SHUTDOWN: usize = 201;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_GETTID: usize = 1001;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_NICE: usize = 1040;
+const SYSCALL_ENDPOINT_CREATE: usize = 1100;
+const SYSCALL_SEND: usize = 1101;
+const SYSCALL_RECV: usize = 1102;
+const SYSCALL_CALL: usize = 1103;
+const SYSCALL_REPLY: usize = 1104;
+const SYSCALL_CAP_COPY: usize = 1200;
+const SYSCALL_CAP_MINT: usize = 1201;
+const SYSCALL_CAP_MOVE: usize = 1202;
+const SYSCALL_CAP_DELETE: usize = 1203;
+const SYSCALL_CAP_REVOKE: usize = 1204;
+const SYSCALL_CAP_IDENTIFY: usize = 1205;
+const SYSCALL_FRAME_ALLOC: usize = 1210;
+const SYSCALL_FRAME_MAP: usize = 1211;
+const SYSCALL_NOTIFICATION_CREATE: usize = 1300;
+const SYSCALL_SIGNAL: usize = 1301;
+const SYSCALL_WAIT: usize = 1302;
+const SYSCALL_POLL: usize = 1303;
+const SYSCALL_NOTIFICATION_BIND: usize = 1304;
+const SYSCALL_IRQ_HANDLER_GET: usize = 1400;
+const SYSCALL_IRQ_HANDLER_SET_NOTIFICATION: usize = 1401;
+const SYSCALL_IRQ_ACK: usize = 1402;
+const SYSCALL_MMIO_MAP: usize = 1410;
+const SYSCALL_SHM_CREATE: usize = 1500;
+const SYSCALL_SHM_MAP: usize = 1501;
+const SYSCALL_GET_IDLE_TIME: usize = 2000;
+const SYSCALL_GET_SCHED_STAT: usize = 2001;
 
-pub fn syscall(id: usize, args: [usize; 3]) -> isize {
+pub fn syscall(id: usize, args: [usize; 6]) -> isize {
     match id {
+        SYSCALL_IOCTL => {
+            sys_ioctl(args[0], args[1], args[2])
+        }
         SYSCALL_READ => {
             sys_read(args[0], args[1] as *const u8, args[2])
         }
@@ -29,12 +104,162 @@ pub fn syscall(id: usize, args: [usize; 3]) -> isize {
         SYSCALL_EXIT => {
             sys_exit(args[0] as i32)
         }
+        SYSCALL_FUTEX => {
+            sys_futex(args[0], args[1], args[2], args[3] as *const TimeSpec)
+        }
+        SYSCALL_NANOSLEEP => {
+            sys_nanosleep(args[0] as *const TimeSpec, args[1] as *mut TimeSpec)
+        }
+        SYSCALL_CLOCK_GETTIME => {
+            sys_clock_gettime(args[0], args[1] as *mut TimeSpec)
+        }
+        SYSCALL_SCHED_SETAFFINITY => {
+            sys_sched_setaffinity(args[0], args[1], args[2] as *const usize)
+        }
         SYSCALL_YIELD => {
             sys_yield()
         }
+        SYSCALL_KILL => {
+            sys_kill(args[0], args[1])
+        }
+        SYSCALL_SIGACTION => {
+            sys_sigaction(args[0], args[1] as *const SignalAction, args[2] as *mut SignalAction)
+        }
+        SYSCALL_SIGPROCMASK => {
+            sys_sigprocmask(args[0], args[1])
+        }
+        SYSCALL_SIGRETURN => {
+            sys_sigreturn()
+        }
+        SYSCALL_SET_PRIORITY => {
+            sys_set_priority(args[0] as isize)
+        }
+        SYSCALL_NICE => {
+            sys_nice(args[0] as isize)
+        }
+        SYSCALL_TIMES => {
+            sys_times(args[0] as *mut Tms)
+        }
+        SYSCALL_GETRUSAGE => {
+            sys_getrusage(args[0] as isize, args[1] as *mut Rusage)
+        }
         SYSCALL_GET_TIME => {
             sys_get_time()
         }
+        SYSCALL_THREAD_CREATE => {
+            sys_thread_create(args[0], args[1])
+        }
+        SYSCALL_GETTID => {
+            sys_gettid()
+        }
+        SYSCALL_WAITTID => {
+            sys_waittid(args[0])
+        }
+        SYSCALL_MUTEX_CREATE => {
+            sys_mutex_create(args[0] != 0)
+        }
+        SYSCALL_MUTEX_LOCK => {
+            sys_mutex_lock(args[0])
+        }
+        SYSCALL_MUTEX_UNLOCK => {
+            sys_mutex_unlock(args[0])
+        }
+        SYSCALL_SEMAPHORE_CREATE => {
+            sys_semaphore_create(args[0])
+        }
+        SYSCALL_SEMAPHORE_UP => {
+            sys_semaphore_up(args[0])
+        }
+        SYSCALL_SEMAPHORE_DOWN => {
+            sys_semaphore_down(args[0])
+        }
+        SYSCALL_CONDVAR_CREATE => {
+            sys_condvar_create()
+        }
+        SYSCALL_CONDVAR_SIGNAL => {
+            sys_condvar_signal(args[0])
+        }
+        SYSCALL_CONDVAR_WAIT => {
+            sys_condvar_wait(args[0], args[1])
+        }
+        SYSCALL_ENDPOINT_CREATE => {
+            sys_endpoint_create()
+        }
+        SYSCALL_SEND => {
+            sys_send(args[0], [args[1], args[2], args[3], args[4]], args[5])
+        }
+        SYSCALL_RECV => {
+            sys_recv(args[0])
+        }
+        SYSCALL_CALL => {
+            sys_call(args[0], [args[1], args[2], args[3], args[4]], args[5])
+        }
+        SYSCALL_REPLY => {
+            sys_reply([args[1], args[2], args[3], args[4]], args[5])
+        }
+        SYSCALL_CAP_COPY => {
+            sys_cap_copy(args[0], args[1])
+        }
+        SYSCALL_CAP_MINT => {
+            sys_cap_mint(args[0], args[1], args[2], args[3])
+        }
+        SYSCALL_CAP_MOVE => {
+            sys_cap_move(args[0], args[1])
+        }
+        SYSCALL_CAP_DELETE => {
+            sys_cap_delete(args[0])
+        }
+        SYSCALL_CAP_REVOKE => {
+            sys_cap_revoke(args[0])
+        }
+        SYSCALL_CAP_IDENTIFY => {
+            sys_cap_identify(args[0])
+        }
+        SYSCALL_FRAME_ALLOC => {
+            sys_frame_alloc()
+        }
+        SYSCALL_FRAME_MAP => {
+            sys_frame_map(args[0], args[1], args[2], args[3])
+        }
+        SYSCALL_NOTIFICATION_CREATE => {
+            sys_notification_create()
+        }
+        SYSCALL_SIGNAL => {
+            sys_signal(args[0], args[1])
+        }
+        SYSCALL_WAIT => {
+            sys_wait(args[0])
+        }
+        SYSCALL_POLL => {
+            sys_poll(args[0])
+        }
+        SYSCALL_NOTIFICATION_BIND => {
+            sys_notification_bind(args[0], args[1])
+        }
+        SYSCALL_IRQ_HANDLER_GET => {
+            sys_irq_handler_get(args[0], args[1])
+        }
+        SYSCALL_IRQ_HANDLER_SET_NOTIFICATION => {
+            sys_irq_handler_set_notification(args[0], args[1])
+        }
+        SYSCALL_IRQ_ACK => {
+            sys_irq_ack(args[0])
+        }
+        SYSCALL_MMIO_MAP => {
+            sys_mmio_map(args[0], args[1], args[2])
+        }
+        SYSCALL_SHM_CREATE => {
+            sys_shm_create(args[0])
+        }
+        SYSCALL_SHM_MAP => {
+            sys_shm_map(args[0], args[1], args[2], args[3])
+        }
+        SYSCALL_GET_IDLE_TIME => {
+            sys_get_idle_time(args[0])
+        }
+        SYSCALL_GET_SCHED_STAT => {
+            sys_get_sched_stat(args[0], args[1] as *mut SchedStat)
+        }
         SYSCALL_GETPID => {
             sys_getpid()
         }
@@ -48,7 +273,19 @@ pub fn syscall(id: usize, args: [usize; 3]) -> isize {
             sys_exec(args[0] as *mut u8)
         }
         SYSCALL_WAITPID => {
-            sys_waitpid(args[0] as isize, args[1] as *mut i32)
+            sys_waitpid(args[0] as isize, args[1] as *mut i32, args[2])
+        }
+        SYSCALL_BRK => {
+            sys_brk(args[0])
+        }
+        SYSCALL_MMAP => {
+            sys_mmap(args[0], args[1], args[2])
+        }
+        SYSCALL_MUNMAP => {
+            sys_munmap(args[0], args[1])
+        }
+        SYSCALL_MPROTECT => {
+            sys_mprotect(args[0], args[1], args[2])
         }
         _ => {
             panic!("Unsupported syscall_id: {}", id);
@@ -1,12 +1,20 @@
 use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
 use crate::drivers::misc::{system_reset, SystemResetOp};
 use crate::loader::get_app_data_by_name;
-use crate::mem::page_table::{translated_refmut, translated_str};
+use crate::mem::address::VirtAddr;
+use crate::mem::memory_set::MapPermission;
 use crate::println;
-use crate::task::{exit_current_and_run_next, suspend_current_and_run_next};
-use crate::task::manager::add_task;
-use crate::task::processor::{current_task, current_user_token};
-use crate::timer::get_time_ms;
+use crate::syscall::errno::{EBUSY, EFAULT, EINVAL, ENOSYS, EPERM, ESRCH};
+use crate::task::{block_current_and_run_next, exit_current_and_run_next, suspend_current_and_run_next};
+use crate::task::manager::{pid2process, set_affinity};
+use crate::task::process::ProcessControlBlock;
+use crate::task::processor::{current_hart, current_process, current_task, idle_time, online_harts, sched_stat, SchedStat};
+use crate::task::scheduler::{Policy, Scheduler, ALL_HARTS};
+use crate::task::usage::{charge_kernel_time, us_to_clocks, Rusage, Tms, RUSAGE_CHILDREN, RUSAGE_SELF};
+use crate::timer::{get_time_ms, get_time_us, TimeSpec};
+use crate::timer::sleep_queue::add_sleeping_task;
 
 pub fn sys_exit(exit_code: i32) -> ! {
     println!("[kernel] Process exited with code {}", exit_code);
@@ -23,57 +31,172 @@ pub fn sys_get_time() -> isize {
     get_time_ms() as isize
 }
 
+/// Returns the microseconds `hart` has spent idle since boot.
+pub fn sys_get_idle_time(hart: usize) -> isize {
+    match idle_time(hart) {
+        Some(time) => time as isize,
+        None => -EINVAL
+    }
+}
+
+/// Copies the scheduling counters of `hart` to `*stat`.
+pub fn sys_get_sched_stat(hart: usize, stat: *mut SchedStat) -> isize {
+    let Some(hart_stat) = sched_stat(hart) else {
+        return -EINVAL;
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if !inner.memory_set.copy_to_user(stat, &hart_stat) {
+        return -EFAULT;
+    }
+    0
+}
+
+/// Blocks the caller for at least `*req`. The remaining time is never written back because
+/// sleeps cannot be interrupted.
+pub fn sys_nanosleep(req: *const TimeSpec, _rem: *mut TimeSpec) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let Some(req) = inner.memory_set.copy_from_user(req) else {
+        return -EFAULT;
+    };
+    drop(inner);
+    let task = current_task().unwrap();
+    let Some(duration) = req.to_us() else {
+        return -EINVAL;
+    };
+    add_sleeping_task(get_time_us().saturating_add(duration), task);
+    block_current_and_run_next();
+    0
+}
+
+/// Reports the time since boot, which is the only clock there is.
+pub fn sys_clock_gettime(_clock_id: usize, tp: *mut TimeSpec) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    if !inner.memory_set.copy_to_user(tp, &TimeSpec::from_us(get_time_us())) {
+        return -EFAULT;
+    }
+    0
+}
+
+/// Fills `*tms` with the CPU time of the caller and of its reaped children, in
+/// `CLOCKS_PER_SEC` ticks, and returns the ticks since boot.
+pub fn sys_times(tms: *mut Tms) -> isize {
+    charge_kernel_time();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let times = Tms {
+        tms_utime: us_to_clocks(inner.usage.user_time),
+        tms_stime: us_to_clocks(inner.usage.kernel_time),
+        tms_cutime: us_to_clocks(inner.children_usage.user_time),
+        tms_cstime: us_to_clocks(inner.children_usage.kernel_time)
+    };
+    if !inner.memory_set.copy_to_user(tms, &times) {
+        return -EFAULT;
+    }
+    us_to_clocks(get_time_us()) as isize
+}
+
+/// Fills `*usage` with what the caller (`RUSAGE_SELF`) or its reaped children
+/// (`RUSAGE_CHILDREN`) have used.
+pub fn sys_getrusage(who: isize, usage: *mut Rusage) -> isize {
+    charge_kernel_time();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let rusage = match who {
+        RUSAGE_SELF => inner.usage.to_rusage(),
+        RUSAGE_CHILDREN => inner.children_usage.to_rusage(),
+        _ => return -EINVAL
+    };
+    if !inner.memory_set.copy_to_user(usage, &rusage) {
+        return -EFAULT;
+    }
+    0
+}
+
+/// Only a process with a single live thread may fork, since the other threads could not be
+/// copied in a consistent state.
 pub fn sys_fork() -> isize {
-    let current_task = current_task().unwrap();
-    let new_task = current_task.fork();
-    let new_pid = new_task.pid.0;
-    let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
-    trap_cx.reg[10] = 0;
-    add_task(new_task);
-    new_pid as isize
+    let process = current_process();
+    if process.inner_exclusive_access().live_threads() > 1 {
+        return -EBUSY;
+    }
+    let child = process.fork();
+    let task = child.inner_exclusive_access().get_task(0).unwrap();
+    task.inner_exclusive_access().get_trap_cx().reg[10] = 0;
+    child.getpid() as isize
 }
 
+/// Longest path `exec` accepts, including the terminating NUL.
+const PATH_MAX: usize = 256;
+
 pub fn sys_exec(path: *const u8) -> isize {
-    let token = current_user_token();
-    let path = translated_str(token, path);
+    let Some(path) = current_process().inner_exclusive_access().memory_set.copy_str_from_user(path, PATH_MAX) else {
+        return -EFAULT;
+    };
     if let Some(data) = get_app_data_by_name(&path) {
-        let task = current_task().unwrap();
-        task.exec(data);
+        let process = current_process();
+        if process.inner_exclusive_access().live_threads() > 1 {
+            return -EBUSY;
+        }
+        process.exec(data);
         0
     } else {
         -1
     }
 }
 
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
-    if inner.children
-        .iter()
-        .find(|p| pid == -1 || pid as usize == p.getpid())
-        .is_none() {
-        return -1;
-    }
-    let pair = inner.children
-        .iter()
-        .enumerate()
-        .find(|(_, p)| {
-            p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
-        });
-    if let Some((index, _)) = pair {
-        let child = inner.children.remove(index);
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        let exit_code = child.inner_exclusive_access().exit_code;
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
-    } else {
-        -2
+/// Return immediately with -2 instead of blocking if no matching child has exited yet.
+const WNOHANG: usize = 1;
+
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: usize) -> isize {
+    if options & !WNOHANG != 0 {
+        return -EINVAL;
+    }
+    let process = current_process();
+    loop {
+        let mut inner = process.inner_exclusive_access();
+        if inner.children
+            .iter()
+            .find(|p| pid == -1 || pid as usize == p.getpid())
+            .is_none() {
+            return -1;
+        }
+        let pair = inner.children
+            .iter()
+            .enumerate()
+            .find(|(_, p)| {
+                p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
+            });
+        if let Some((index, _)) = pair {
+            if !inner.memory_set.fault_in(
+                VirtAddr::from(exit_code_ptr as usize), size_of::<i32>(), MapPermission::W
+            ) {
+                return -EFAULT;
+            }
+            let child = inner.children.remove(index);
+            let found_pid = child.getpid();
+            let child_inner = child.inner_exclusive_access();
+            let exit_code = child_inner.exit_code;
+            inner.children_usage.add(&child_inner.usage);
+            inner.children_usage.add(&child_inner.children_usage);
+            drop(child_inner);
+            // Faulted in above, before the child was taken, so this cannot fail.
+            let _ = inner.memory_set.copy_to_user(exit_code_ptr, &exit_code);
+            return found_pid as isize;
+        }
+        if options & WNOHANG != 0 {
+            return -2;
+        }
+        inner.wait_queue.push(current_task().unwrap());
+        drop(inner);
+        block_current_and_run_next();
     }
 }
 
 pub fn sys_getpid() -> isize {
-    current_task().unwrap().pid.0 as isize
+    current_process().getpid() as isize
 }
 
 pub fn sys_shutdown(code: i32) -> isize {
@@ -82,4 +205,83 @@ pub fn sys_shutdown(code: i32) -> isize {
     } else {
         unsafe { system_reset(SystemResetOp::ShutdownError) }
     }
-}
\ No newline at end of file
+}
+/// Sets the scheduling priority of the calling thread, see `task::scheduler`. Returns
+/// `prio`, or -ENOSYS if the scheduler ignores priorities.
+pub fn sys_set_priority(prio: isize) -> isize {
+    if !Policy::USES_PRIORITY {
+        return -ENOSYS;
+    }
+    if prio <= 0 {
+        return -EINVAL;
+    }
+    current_task().unwrap().inner_exclusive_access().sched.priority = prio as usize;
+    prio
+}
+
+/// Adds `inc` to the nice value of the calling thread, which also resets its priority to
+/// the one that goes with the new nice value. Returns the new nice value, or -ENOSYS if the
+/// scheduler ignores priorities.
+pub fn sys_nice(inc: isize) -> isize {
+    if !Policy::USES_PRIORITY {
+        return -ENOSYS;
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let nice = inner.sched.nice.saturating_add(inc);
+    inner.sched.set_nice(nice);
+    inner.sched.nice
+}
+
+/// Whether `process` is `ancestor` itself or one of its descendants.
+fn descends_from(process: &Arc<ProcessControlBlock>, ancestor: &Arc<ProcessControlBlock>) -> bool {
+    let mut process = process.clone();
+    while !Arc::ptr_eq(&process, ancestor) {
+        let parent = process.inner_exclusive_access().parent.as_ref().and_then(|parent| parent.upgrade());
+        let Some(parent) = parent else {
+            return false;
+        };
+        process = parent;
+    }
+    true
+}
+
+/// Restricts the calling thread, or every thread of process `pid` unless `pid` is 0, to the
+/// harts in the bit mask at `mask`, of which `len` bytes are readable. Threads have no ids
+/// of their own outside their process, so unlike on Linux a pid stands for all its threads.
+/// Only the caller's own process and its descendants may be restricted.
+pub fn sys_sched_setaffinity(pid: usize, len: usize, mask: *const usize) -> isize {
+    if len < size_of::<usize>() {
+        return -EINVAL;
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let Some(affinity) = inner.memory_set.copy_from_user(mask) else {
+        return -EFAULT;
+    };
+    let affinity = affinity & ALL_HARTS;
+    drop(inner);
+    if affinity & online_harts() == 0 {
+        return -EINVAL;
+    }
+    let tasks: Vec<_> = if pid == 0 {
+        vec![current_task().unwrap()]
+    } else {
+        let Some(target) = pid2process(pid) else {
+            return -ESRCH;
+        };
+        if !descends_from(&target, &process) {
+            return -EPERM;
+        }
+        let inner = target.inner_exclusive_access();
+        inner.tasks.iter().flatten().cloned().collect()
+    };
+    for task in tasks.iter() {
+        set_affinity(task, affinity);
+    }
+    // Move right away if the calling thread may no longer run here.
+    if !current_task().unwrap().may_run_on(current_hart()) {
+        suspend_current_and_run_next();
+    }
+    0
+}
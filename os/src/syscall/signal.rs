@@ -0,0 +1,83 @@
+use alloc::sync::Arc;
+use crate::syscall::errno::{EFAULT, EINVAL, EPERM, ESRCH};
+use crate::task::INITPROC;
+use crate::task::manager::pid2process;
+use crate::task::processor::{current_process, current_task, current_trap_cx};
+use crate::task::signal::{is_fatal, send_signal, SignalAction, SignalFlags, MAX_SIG, SIGKILL};
+
+const SIG_BLOCK: usize = 0;
+const SIG_UNBLOCK: usize = 1;
+const SIG_SETMASK: usize = 2;
+
+/// Sends `signum` to process `pid`. Signal 0 only checks that the process exists.
+/// initproc only takes signals it has a handler for or ignores, since nothing could reap
+/// orphans after it.
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    if signum > MAX_SIG {
+        return -EINVAL;
+    }
+    let Some(process) = pid2process(pid) else {
+        return -ESRCH;
+    };
+    if signum != 0 && Arc::ptr_eq(&process, &INITPROC) && is_fatal(&process, signum) {
+        return -EPERM;
+    }
+    if signum != 0 {
+        send_signal(&process, signum);
+    }
+    0
+}
+
+/// Installs `*action` for `signum` unless it is null, after storing the previous action in
+/// `*old_action` unless that is null. SIGKILL cannot be caught or ignored.
+pub fn sys_sigaction(signum: usize, action: *const SignalAction, old_action: *mut SignalAction) -> isize {
+    if signum == 0 || signum > MAX_SIG || signum == SIGKILL {
+        return -EINVAL;
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let old = inner.signal_actions[signum];
+    if !old_action.is_null() && !inner.memory_set.copy_to_user(old_action, &old) {
+        return -EFAULT;
+    }
+    if !action.is_null() {
+        let Some(mut action) = inner.memory_set.copy_from_user(action) else {
+            return -EFAULT;
+        };
+        action.mask.remove(SignalFlags::SIGKILL);
+        inner.signal_actions[signum] = action;
+    }
+    0
+}
+
+/// Blocks, unblocks or replaces the set of masked signals and returns the previous set.
+/// SIGKILL is never masked.
+pub fn sys_sigprocmask(how: usize, set: usize) -> isize {
+    let set = SignalFlags::from_bits_truncate(set as u32);
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let old = inner.signal_mask;
+    let mut mask = match how {
+        SIG_BLOCK => old | set,
+        SIG_UNBLOCK => old - set,
+        SIG_SETMASK => set,
+        _ => return -EINVAL
+    };
+    mask.remove(SignalFlags::SIGKILL);
+    inner.signal_mask = mask;
+    old.bits() as isize
+}
+
+/// Returns from a signal handler to where the thread was interrupted, restoring the signal
+/// mask from before the handler ran.
+pub fn sys_sigreturn() -> isize {
+    let task = current_task().unwrap();
+    let Some(frame) = task.inner_exclusive_access().signal_frame.take() else {
+        return -EINVAL;
+    };
+    task.process().inner_exclusive_access().signal_mask = frame.mask;
+    let trap_cx = current_trap_cx();
+    *trap_cx = frame.trap_cx;
+    // The return value lands in a0, which has to keep the interrupted value.
+    trap_cx.reg[10] as isize
+}
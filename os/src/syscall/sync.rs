@@ -0,0 +1,149 @@
+use alloc::sync::Arc;
+use crate::mem::address::VirtAddr;
+use crate::mem::memory_set::MapPermission;
+use crate::mem::page_table::PageTable;
+use crate::sync::condvar::Condvar;
+use crate::sync::futex::{futex_wait, futex_wake, FutexWait};
+use crate::sync::mutex::{Mutex, MutexBlocking, MutexSpin};
+use crate::sync::semaphore::Semaphore;
+use crate::syscall::errno::{EAGAIN, EFAULT, EINVAL, ETIMEDOUT};
+use crate::task::processor::current_process;
+use crate::timer::{get_time_us, TimeSpec};
+
+const FUTEX_WAIT: usize = 0;
+const FUTEX_WAKE: usize = 1;
+
+fn mutex(id: usize) -> Option<Arc<dyn Mutex>> {
+    current_process().inner_exclusive_access().mutex_list.get(id).cloned()
+}
+
+fn semaphore(id: usize) -> Option<Arc<Semaphore>> {
+    current_process().inner_exclusive_access().semaphore_list.get(id).cloned()
+}
+
+fn condvar(id: usize) -> Option<Arc<Condvar>> {
+    current_process().inner_exclusive_access().condvar_list.get(id).cloned()
+}
+
+/// Creates a mutex for the threads of the current process and returns its id. A blocking
+/// mutex puts waiting threads to sleep, the other kind makes them yield in a loop.
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    let mutex: Arc<dyn Mutex> = if blocking {
+        Arc::new(MutexBlocking::new())
+    } else {
+        Arc::new(MutexSpin::new())
+    };
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.mutex_list.push(mutex);
+    (inner.mutex_list.len() - 1) as isize
+}
+
+pub fn sys_mutex_lock(id: usize) -> isize {
+    let Some(mutex) = mutex(id) else {
+        return -EINVAL;
+    };
+    mutex.lock();
+    0
+}
+
+pub fn sys_mutex_unlock(id: usize) -> isize {
+    let Some(mutex) = mutex(id) else {
+        return -EINVAL;
+    };
+    mutex.unlock();
+    0
+}
+
+/// Creates a semaphore holding `count` units and returns its id.
+pub fn sys_semaphore_create(count: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.semaphore_list.push(Arc::new(Semaphore::new(count)));
+    (inner.semaphore_list.len() - 1) as isize
+}
+
+pub fn sys_semaphore_up(id: usize) -> isize {
+    let Some(semaphore) = semaphore(id) else {
+        return -EINVAL;
+    };
+    semaphore.up();
+    0
+}
+
+pub fn sys_semaphore_down(id: usize) -> isize {
+    let Some(semaphore) = semaphore(id) else {
+        return -EINVAL;
+    };
+    semaphore.down();
+    0
+}
+
+pub fn sys_condvar_create() -> isize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    inner.condvar_list.push(Arc::new(Condvar::new()));
+    (inner.condvar_list.len() - 1) as isize
+}
+
+pub fn sys_condvar_signal(id: usize) -> isize {
+    let Some(condvar) = condvar(id) else {
+        return -EINVAL;
+    };
+    condvar.signal();
+    0
+}
+
+/// Waits on condition variable `id`, releasing mutex `mutex_id` while asleep. The caller
+/// must hold the mutex.
+pub fn sys_condvar_wait(id: usize, mutex_id: usize) -> isize {
+    let (Some(condvar), Some(mutex)) = (condvar(id), mutex(mutex_id)) else {
+        return -EINVAL;
+    };
+    condvar.wait(mutex);
+    0
+}
+
+/// `FUTEX_WAIT` blocks while the 32-bit word at `uaddr` still holds `val`, for at most
+/// `*timeout` unless it is null. `FUTEX_WAKE` wakes up to `val` waiters and returns how many
+/// it woke. The word is identified by its physical address, so it may be shared by threads
+/// as well as by processes that map the same memory.
+pub fn sys_futex(uaddr: usize, op: usize, val: usize, timeout: *const TimeSpec) -> isize {
+    if !uaddr.is_multiple_of(size_of::<u32>()) {
+        return -EINVAL;
+    }
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let va = VirtAddr::from(uaddr);
+    // Break copy-on-write sharing first, so that waiter and waker agree on the frame. The word
+    // may as well be read-only, which only the second check refuses.
+    let _ = inner.memory_set.fault_in(va, size_of::<u32>(), MapPermission::W);
+    if !inner.memory_set.fault_in(va, size_of::<u32>(), MapPermission::R) {
+        return -EFAULT;
+    }
+    let page_table = PageTable::from_token(inner.memory_set.token());
+    let pa = page_table.translate_va(va).unwrap();
+    match op {
+        FUTEX_WAIT => {
+            let deadline = if timeout.is_null() {
+                None
+            } else {
+                let Some(timeout) = inner.memory_set.copy_from_user(timeout) else {
+                    return -EFAULT;
+                };
+                let Some(duration) = timeout.to_us() else {
+                    return -EINVAL;
+                };
+                Some(get_time_us().saturating_add(duration))
+            };
+            drop(inner);
+            match futex_wait(pa.into(), val as u32, deadline) {
+                FutexWait::Woken => 0,
+                FutexWait::TimedOut => -ETIMEDOUT,
+                FutexWait::ValueChanged => -EAGAIN
+            }
+        }
+        FUTEX_WAKE => futex_wake(pa.into(), val) as isize,
+        _ => -EINVAL
+    }
+}
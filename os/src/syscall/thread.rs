@@ -0,0 +1,65 @@
+use crate::mem::memory_set::KERNEL_SPACE;
+use crate::syscall::errno::{EDEADLK, ENOMEM, ESRCH};
+use crate::task::block_current_and_run_next;
+use crate::task::manager::add_task;
+use crate::task::processor::{current_process, current_task};
+use crate::trap::context::TrapContext;
+use crate::trap::trap_handler;
+
+/// Starts a thread in the current process that runs `entry(arg)` on a fresh user stack.
+/// Returns its tid.
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    let process = current_process();
+    let Some(task) = process.add_thread() else {
+        return -ENOMEM;
+    };
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.sched = current_task().unwrap().inner_exclusive_access().sched.inherit();
+    let res = task_inner.res.as_ref().unwrap();
+    let tid = res.tid;
+    let ustack_top = res.ustack_top().unwrap();
+    let trap_cx = task_inner.get_trap_cx();
+    *trap_cx = TrapContext::app_init_context(
+        entry,
+        ustack_top,
+        KERNEL_SPACE.exclusive_access().token(),
+        task.kernal_stack.get_top(),
+        trap_handler as *const () as usize
+    );
+    trap_cx.reg[10] = arg;
+    drop(task_inner);
+    add_task(task);
+    tid as isize
+}
+
+pub fn sys_gettid() -> isize {
+    current_task().unwrap().tid as isize
+}
+
+/// Blocks until thread `tid` of the current process exits, then frees its tid and returns
+/// its exit code. A thread cannot wait for itself.
+pub fn sys_waittid(tid: usize) -> isize {
+    let task = current_task().unwrap();
+    if task.tid == tid {
+        return -EDEADLK;
+    }
+    let process = task.process();
+    loop {
+        let mut inner = process.inner_exclusive_access();
+        let Some(waited) = inner.get_task(tid) else {
+            return -ESRCH;
+        };
+        let waited_inner = waited.inner_exclusive_access();
+        // The tid can only be reused once the thread has unmapped its stack and trap context.
+        if let (Some(exit_code), None) = (waited_inner.exit_code, waited_inner.res.as_ref()) {
+            drop(waited_inner);
+            inner.tasks[tid] = None;
+            inner.tid_allocator.dealloc(tid);
+            return exit_code as isize;
+        }
+        drop(waited_inner);
+        inner.thread_wait_queue.push(task.clone());
+        drop(inner);
+        block_current_and_run_next();
+    }
+}
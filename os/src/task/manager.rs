@@ -1,37 +1,132 @@
-use alloc::collections::VecDeque;
-use alloc::sync::Arc;
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
 use lazy_static::lazy_static;
-use crate::sync::up::UPSafeCell;
-use crate::task::task::ProcessControlBlock;
+use crate::config::CPUS;
+use crate::sync::spin::SpinLock;
+use crate::task::process::ProcessControlBlock;
+use crate::task::processor::{current_hart, idle_harts, online_harts};
+use crate::task::scheduler::{Policy, Scheduler};
+use crate::task::task::TaskControlBlock;
+use crate::trap::ipi::wake_hart;
 
+/// Ready threads of a hart, in the order the scheduling policy picked at build time runs
+/// them.
 pub struct TaskManager {
-    ready_queue: VecDeque<Arc<ProcessControlBlock>>
+    scheduler: Policy
 }
 
 impl TaskManager {
     pub fn new() -> Self {
         Self {
-            ready_queue: VecDeque::new()
+            scheduler: Policy::new()
         }
     }
-    pub fn add(&mut self, process: Arc<ProcessControlBlock>) {
-        self.ready_queue.push_back(process);
+    pub fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.scheduler.add(task);
     }
-    pub fn fetch(&mut self) -> Option<Arc<ProcessControlBlock>> {
-        self.ready_queue.pop_front()
+    pub fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.scheduler.fetch()
+    }
+    pub fn remove(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        self.scheduler.remove(task)
+    }
+    pub fn tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        self.scheduler.tick(task)
+    }
+    pub fn len(&self) -> usize {
+        self.scheduler.len()
+    }
+    pub fn steal(&mut self, hart: usize) -> Option<Arc<TaskControlBlock>> {
+        self.scheduler.steal(hart)
     }
 }
 
 lazy_static! {
-    pub static ref TASK_MANAGER: UPSafeCell<TaskManager> = unsafe {
-        UPSafeCell::new(TaskManager::new())
+    /// Indexed by hart id. No code holds two of them at once.
+    static ref TASK_MANAGERS: Vec<SpinLock<TaskManager>> =
+        (0..CPUS).map(|_| SpinLock::new(TaskManager::new())).collect();
+    /// Processes that have not exited yet, by pid.
+    static ref PID2PROCESS: SpinLock<BTreeMap<usize, Weak<ProcessControlBlock>>> =
+        SpinLock::new(BTreeMap::new());
+}
+
+/// Threads queued on `hart`, plus the one it runs unless it idles.
+fn load(hart: usize) -> usize {
+    let running = idle_harts() & (1 << hart) == 0;
+    TASK_MANAGERS[hart].exclusive_access().len() + running as usize
+}
+
+/// Queues `task` on the hart it ran on last, whose cache may still hold its data, or else on
+/// the least loaded hart it may run on. An idle hart is woken up to run or steal it.
+pub fn add_task(task: Arc<TaskControlBlock>) {
+    let inner = task.inner_exclusive_access();
+    let allowed = inner.sched.affinity & online_harts();
+    let last_hart = inner.sched.last_hart;
+    drop(inner);
+    let hart = match last_hart {
+        Some(hart) if allowed & (1 << hart) != 0 => hart,
+        _ => (0..CPUS)
+            .filter(|hart| allowed & (1 << hart) != 0)
+            .min_by_key(|&hart| load(hart))
+            .unwrap_or_else(current_hart)
     };
+    TASK_MANAGERS[hart].exclusive_access().add(task);
+    let idle = idle_harts() & allowed;
+    if idle & (1 << hart) != 0 {
+        wake_hart(hart);
+    } else if idle != 0 {
+        wake_hart(idle.trailing_zeros() as usize);
+    }
+}
+
+/// Takes the next thread to run on this hart. With nothing queued here, steals one from the
+/// busiest hart that has one that may run here.
+pub fn fetch_task() -> Option<Arc<TaskControlBlock>> {
+    let hart = current_hart();
+    if let Some(task) = TASK_MANAGERS[hart].exclusive_access().fetch() {
+        return Some(task);
+    }
+    let mut victims: [(usize, usize); CPUS] = core::array::from_fn(|victim| {
+        (TASK_MANAGERS[victim].exclusive_access().len(), victim)
+    });
+    victims.sort_unstable_by(|a, b| b.cmp(a));
+    victims.into_iter()
+        .filter(|&(len, victim)| len > 0 && victim != hart)
+        .find_map(|(_, victim)| TASK_MANAGERS[victim].exclusive_access().steal(hart))
+}
+
+/// Tells the scheduler of this hart that the timer interrupted `task`. Returns whether to
+/// preempt it.
+pub fn tick_task(task: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGERS[current_hart()].exclusive_access().tick(task)
 }
 
-pub fn add_task(process: Arc<ProcessControlBlock>) {
-    TASK_MANAGER.exclusive_access().add(process);
+/// Takes `task` off the ready queue it is in, if any. Returns whether it was queued.
+pub fn remove_task(task: &Arc<TaskControlBlock>) -> bool {
+    TASK_MANAGERS.iter().any(|manager| manager.exclusive_access().remove(task))
+}
+
+/// Restricts `task` to the harts in `affinity`. A thread queued on a hart it may no longer
+/// run on is queued again elsewhere, a running one moves the next time it is switched out.
+pub fn set_affinity(task: &Arc<TaskControlBlock>, affinity: usize) {
+    task.inner_exclusive_access().sched.affinity = affinity;
+    let queued = (0..CPUS)
+        .filter(|hart| affinity & (1 << hart) == 0)
+        .any(|hart| TASK_MANAGERS[hart].exclusive_access().remove(task));
+    if queued {
+        add_task(task.clone());
+    }
 }
 
-pub fn fetch_task() -> Option<Arc<ProcessControlBlock>> {
-    TASK_MANAGER.exclusive_access().fetch()
-}
\ No newline at end of file
+pub fn insert_into_pid2process(pid: usize, process: &Arc<ProcessControlBlock>) {
+    PID2PROCESS.exclusive_access().insert(pid, Arc::downgrade(process));
+}
+
+pub fn pid2process(pid: usize) -> Option<Arc<ProcessControlBlock>> {
+    PID2PROCESS.exclusive_access().get(&pid).and_then(|process| process.upgrade())
+}
+
+pub fn remove_from_pid2process(pid: usize) {
+    PID2PROCESS.exclusive_access().remove(&pid);
+}
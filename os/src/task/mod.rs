@@ -1,57 +1,239 @@
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::Ordering;
 use lazy_static::lazy_static;
+use crate::drivers::plic::release_dead_routes;
+use crate::ipc::abort_call;
 use crate::loader::get_app_data_by_name;
 use crate::task::context::TaskContext;
-use crate::task::manager::add_task;
+use crate::task::manager::{add_task, remove_from_pid2process, remove_task};
 use crate::task::processor::{current_task, schedule};
-use crate::task::task::{ProcessControlBlock, TaskStatus};
+use crate::task::process::ProcessControlBlock;
+use crate::task::signal::{send_signal, SIGCHLD};
+use crate::task::task::{TaskControlBlock, TaskStatus};
+use crate::task::usage::count_switch;
 
 pub mod context;
 mod switch;
 pub mod task;
 pub mod pid;
+pub mod process;
 pub mod manager;
 pub mod processor;
+pub mod scheduler;
+pub mod signal;
+pub mod usage;
+pub mod wait_queue;
 
 
 lazy_static! {
-    pub static ref INITPROC: Arc<ProcessControlBlock> = Arc::new(
-        ProcessControlBlock::new(get_app_data_by_name("initproc").unwrap())
-    );
+    pub static ref INITPROC: Arc<ProcessControlBlock> = {
+        let initproc = ProcessControlBlock::new(get_app_data_by_name("initproc").unwrap());
+        initproc.grant_address_space();
+        initproc
+    };
 }
 
+/// Creates the first process, whose thread is then ready to run.
 pub fn add_initproc() {
-    add_task(INITPROC.clone());
+    let _initproc = INITPROC.clone();
 }
 
+/// Puts the current task back on the ready queue and runs another one. A task whose process
+/// another hart ended in the meantime is not queued again.
 pub fn suspend_current_and_run_next() {
+    count_switch(false);
     let task = current_task().unwrap();
     let mut task_inner =  task.inner_exclusive_access();
     let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    if task_inner.task_status == TaskStatus::Exited {
+        drop(task_inner);
+        drop(task);
+        schedule(task_cx_ptr);
+        return;
+    }
     task_inner.task_status = TaskStatus::Ready;
     drop(task_inner);
     add_task(task);
     schedule(task_cx_ptr);
 }
 
-pub fn exit_current_and_run_next(exit_code: i32) {
+/// Parks the current task until someone calls `wakeup_task` on it. The caller must have
+/// put the task somewhere it can be found again, usually a `WaitQueue`. Returns at once if
+/// another hart woke it up before it got here.
+pub fn block_current_and_run_next() {
     let task = current_task().unwrap();
     let mut task_inner = task.inner_exclusive_access();
-    task_inner.task_status = TaskStatus::Zombie;
-    task_inner.exit_code = exit_code;
-
-    {
-        let mut initproc_inner = INITPROC.inner_exclusive_access();
-        for child in task_inner.children.iter() {
-            child.inner_exclusive_access().parent = Some(Arc::downgrade(&INITPROC));
-            initproc_inner.children.push(child.clone());
+    match task_inner.task_status {
+        TaskStatus::Ready => {
+            task_inner.task_status = TaskStatus::Running;
+            return;
         }
+        TaskStatus::Running => task_inner.task_status = TaskStatus::Blocked,
+        TaskStatus::Blocked | TaskStatus::Exited => {}
     }
+    let task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+    drop(task_inner);
+    drop(task);
+    count_switch(true);
+    schedule(task_cx_ptr);
+}
 
-    task_inner.children.clear();
-    task_inner.memory_set.recycled_data_pages();
+/// Makes a blocked task runnable again. A task that is still running, on its way to
+/// `block_current_and_run_next`, is only marked ready so that it does not block. A task that
+/// has exited in the meantime, because its process did, is left alone.
+pub fn wakeup_task(task: Arc<TaskControlBlock>) {
+    let mut task_inner = task.inner_exclusive_access();
+    match task_inner.task_status {
+        TaskStatus::Blocked => task_inner.task_status = TaskStatus::Ready,
+        TaskStatus::Running => {
+            task_inner.task_status = TaskStatus::Ready;
+            return;
+        }
+        TaskStatus::Ready | TaskStatus::Exited => return
+    }
     drop(task_inner);
+    add_task(task);
+}
+
+/// Switches away for good if another hart ended the current thread's process while the
+/// thread ran.
+pub fn run_next_if_exited() {
+    if current_task().unwrap().is_exited() {
+        let mut _unused = TaskContext::zero_init();
+        schedule(&mut _unused as *mut TaskContext);
+    }
+}
+
+/// Ends the current thread. When the first thread of a process exits, the whole process
+/// exits with it.
+pub fn exit_current_and_run_next(exit_code: i32) {
+    let task = current_task().unwrap();
+    let process = task.process();
+    finish_thread(&task, exit_code);
+    if task.tid == 0 {
+        exit_process(&process, exit_code);
+    } else {
+        process.inner_exclusive_access().thread_wait_queue.wake_all();
+    }
+    drop(process);
     drop(task);
     let mut _unused = TaskContext::zero_init();
     schedule(&mut _unused as *mut TaskContext);
-}
\ No newline at end of file
+}
+
+/// Ends every thread of the current process, e.g. after one of them faulted.
+pub fn exit_process_and_run_next(exit_code: i32) {
+    let process = current_task().unwrap().process();
+    exit_process(&process, exit_code);
+    drop(process);
+    let mut _unused = TaskContext::zero_init();
+    schedule(&mut _unused as *mut TaskContext);
+}
+
+/// Marks `task` exited and frees the parts of the address space only it used, unless another
+/// hart still runs it; `reap_thread` does that once the hart has switched away. The control
+/// block itself stays in the process until the thread is joined or the process reaped.
+fn finish_thread(task: &Arc<TaskControlBlock>, exit_code: i32) {
+    let mut task_inner = task.inner_exclusive_access();
+    if task_inner.task_status == TaskStatus::Exited {
+        return;
+    }
+    task_inner.task_status = TaskStatus::Exited;
+    task_inner.exit_code = Some(exit_code);
+    let caller = task_inner.ipc.reply_to.take();
+    drop(task_inner);
+    if let Some(caller) = caller {
+        abort_call(caller);
+    }
+    remove_task(task);
+    if !runs_elsewhere(task) {
+        release_thread(task);
+    }
+}
+
+fn release_thread(task: &TaskControlBlock) {
+    let res = task.inner_exclusive_access().res.take();
+    drop(res);
+}
+
+/// Whether a hart other than this one runs `task`.
+fn runs_elsewhere(task: &Arc<TaskControlBlock>) -> bool {
+    task.on_cpu.load(Ordering::SeqCst)
+        && !current_task().is_some_and(|current| Arc::ptr_eq(&current, task))
+}
+
+/// Ends every thread of `process`. Threads running on other harts notice at their next trap
+/// and the process turns into a zombie once the last of them has switched away. It must not
+/// be the current process unless the caller schedules away afterwards.
+pub fn exit_process(process: &Arc<ProcessControlBlock>, exit_code: i32) {
+    let mut process_inner = process.inner_exclusive_access();
+    if process_inner.is_exiting {
+        return;
+    }
+    process_inner.is_exiting = true;
+    process_inner.exit_code = exit_code;
+    let tasks: Vec<_> = process_inner.tasks.iter().flatten().cloned().collect();
+    drop(process_inner);
+    remove_from_pid2process(process.getpid());
+    for task in tasks.iter() {
+        finish_thread(task, exit_code);
+    }
+    if !tasks.iter().any(runs_elsewhere) {
+        make_zombie(process);
+    }
+}
+
+/// Called by a hart that has switched away from `task` after it exited, to finish what
+/// `exit_process` could not while the thread still ran.
+pub fn reap_thread(task: &Arc<TaskControlBlock>) {
+    release_thread(task);
+    let Some(process) = task.process.upgrade() else {
+        return;
+    };
+    let process_inner = process.inner_exclusive_access();
+    if !process_inner.is_exiting {
+        return;
+    }
+    let tasks: Vec<_> = process_inner.tasks.iter().flatten().cloned().collect();
+    drop(process_inner);
+    if !tasks.iter().any(runs_elsewhere) {
+        make_zombie(&process);
+    }
+}
+
+/// Turns `process`, none of whose threads runs any more, into a zombie for its parent to
+/// reap.
+fn make_zombie(process: &Arc<ProcessControlBlock>) {
+    let mut process_inner = process.inner_exclusive_access();
+    if process_inner.is_zombie {
+        return;
+    }
+    process_inner.is_zombie = true;
+    let children = core::mem::take(&mut process_inner.children);
+    let parent = process_inner.parent.as_ref().and_then(|parent| parent.upgrade());
+    process_inner.cspace.clear();
+    process_inner.memory_set.recycled_data_pages();
+    drop(process_inner);
+    release_dead_routes();
+
+    {
+        let mut initproc_inner = INITPROC.inner_exclusive_access();
+        let mut has_zombie = false;
+        for child in children {
+            let mut child_inner = child.inner_exclusive_access();
+            child_inner.parent = Some(Arc::downgrade(&INITPROC));
+            has_zombie |= child_inner.is_zombie();
+            drop(child_inner);
+            initproc_inner.children.push(child);
+        }
+        if has_zombie {
+            initproc_inner.wait_queue.wake_all();
+        }
+    }
+
+    if let Some(parent) = parent {
+        parent.inner_exclusive_access().wait_queue.wake_all();
+        send_signal(&parent, SIGCHLD);
+    }
+}
@@ -3,50 +3,51 @@ use lazy_static::lazy_static;
 use crate::config::kernel_stack_position;
 use crate::mem::address::VirtAddr;
 use crate::mem::memory_set::{MapPermission, KERNEL_SPACE};
-use crate::sync::up::UPSafeCell;
+use crate::sync::spin::SpinLock;
 
-pub struct PidHandle(pub usize);
-
-struct PidAllocator {
+/// Hands out small integer ids, reusing freed ones first.
+pub struct RecycleAllocator {
     cur: usize,
     recycled: Vec<usize>
 }
 
-pub struct KernalStack {
-    pid: usize
-}
-
-lazy_static! {
-    static ref PID_ALLOCATOR : UPSafeCell<PidAllocator> = unsafe {
-        UPSafeCell::new(PidAllocator::new())
-    };
-}
-
-impl PidAllocator {
-    pub fn new() -> Self {
+impl RecycleAllocator {
+    /// The first id handed out is `first`.
+    pub fn new(first: usize) -> Self {
         Self {
-            cur: 0,
+            cur: first,
             recycled: Vec::new()
         }
     }
-    pub fn alloc(&mut self) -> PidHandle {
-        if let Some(pid) = self.recycled.pop() {
-            PidHandle(pid)
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
         } else {
             self.cur += 1;
-            PidHandle(self.cur)
+            self.cur - 1
         }
     }
-    pub fn dealloc(&mut self, pid: usize) {
-        assert!(pid <= self.cur);
-        assert!(self.recycled.iter().find(|ppid| **ppid == pid).is_none(),
-            "pid {} was not deallocated", pid);
-        self.recycled.push(pid);
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.cur);
+        assert!(!self.recycled.contains(&id), "id {} was deallocated twice", id);
+        self.recycled.push(id);
     }
 }
 
+pub struct PidHandle(pub usize);
+
+pub struct KernalStack {
+    id: usize
+}
+
+lazy_static! {
+    static ref PID_ALLOCATOR: SpinLock<RecycleAllocator> = SpinLock::new(RecycleAllocator::new(1));
+    static ref KSTACK_ALLOCATOR: SpinLock<RecycleAllocator> =
+        SpinLock::new(RecycleAllocator::new(0));
+}
+
 pub fn pid_alloc() -> PidHandle {
-    PID_ALLOCATOR.exclusive_access().alloc()
+    PidHandle(PID_ALLOCATOR.exclusive_access().alloc())
 }
 
 impl Drop for PidHandle {
@@ -56,21 +57,22 @@ impl Drop for PidHandle {
 }
 
 impl KernalStack {
-    pub fn new(pid_handle: &PidHandle) -> Self {
-        let pid = pid_handle.0;
-        let (kstack_bottom, kstack_top) = kernel_stack_position(pid);
+    /// Maps a fresh kernel stack for a new thread.
+    pub fn new() -> Self {
+        let id = KSTACK_ALLOCATOR.exclusive_access().alloc();
+        let (kstack_bottom, kstack_top) = kernel_stack_position(id);
         KERNEL_SPACE.exclusive_access().insert_framed_data(
             kstack_bottom.into(),
             kstack_top.into(),
             MapPermission::R |  MapPermission::W
         );
         KernalStack {
-            pid
+            id
         }
     }
     
     pub fn get_top(&self) -> usize {
-        let (_, kernel_stack_top) = kernel_stack_position(self.pid);
+        let (_, kernel_stack_top) = kernel_stack_position(self.id);
         kernel_stack_top
     }
     
@@ -85,8 +87,9 @@ impl KernalStack {
 
 impl Drop for KernalStack {
     fn drop(&mut self) {
-        let (kstack_bottom, _) = kernel_stack_position(self.pid);
+        let (kstack_bottom, _) = kernel_stack_position(self.id);
         let kstack_bottom_va: VirtAddr = kstack_bottom.into();
         KERNEL_SPACE.exclusive_access().remove_area_with_start_vpn(kstack_bottom_va.into());
+        KSTACK_ALLOCATOR.exclusive_access().dealloc(self.id);
     }
-}
\ No newline at end of file
+}
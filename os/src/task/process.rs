@@ -0,0 +1,249 @@
+use crate::cap::{Capability, CapObject, CAP_ADDRESS_SPACE, CAP_IRQ_CONTROL, CAP_NAME_SERVER};
+use crate::cap::cspace::CSpace;
+use crate::config::trap_context_position;
+use crate::ipc::endpoint::Endpoint;
+use crate::mem::address::VirtAddr;
+use crate::mem::memory_set::{MemorySet, KERNEL_SPACE};
+use crate::sync::condvar::Condvar;
+use crate::sync::mutex::Mutex;
+use crate::sync::semaphore::Semaphore;
+use crate::sync::spin::{SpinLock, SpinLockGuard};
+use crate::task::manager::{add_task, insert_into_pid2process};
+use crate::task::pid::{pid_alloc, PidHandle, RecycleAllocator};
+use crate::task::signal::{SignalAction, SignalFlags, MAX_SIG, SIG_IGN};
+use crate::task::task::{TaskControlBlock, TaskUserRes};
+use crate::task::usage::CpuUsage;
+use crate::task::wait_queue::WaitQueue;
+use crate::trap::context::TrapContext;
+use crate::trap::trap_handler;
+use alloc::sync::Arc;
+use alloc::sync::Weak;
+use alloc::vec::Vec;
+
+/// What the threads of a process share: the address space, capabilities and the place in
+/// the process tree.
+/// A process's lock is always taken before the locks of threads, never while holding one.
+pub struct ProcessControlBlock {
+    pub pid: PidHandle,
+    inner: SpinLock<ProcessControlBlockInner>,
+}
+
+pub struct ProcessControlBlockInner {
+    /// Set by `exit_process`. The process becomes a zombie once none of its threads runs on
+    /// a hart any more.
+    pub is_exiting: bool,
+    pub is_zombie: bool,
+    pub memory_set: MemorySet,
+    pub heap_bottom: usize,
+    pub program_brk: usize,
+    pub parent: Option<Weak<ProcessControlBlock>>,
+    pub children: Vec<Arc<ProcessControlBlock>>,
+    /// Woken whenever one of the children becomes a zombie.
+    pub wait_queue: WaitQueue,
+    pub cspace: CSpace,
+    pub exit_code: i32,
+    /// Indexed by tid. Exited threads stay here until they are joined.
+    pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
+    pub tid_allocator: RecycleAllocator,
+    /// Woken whenever one of the threads exits.
+    pub thread_wait_queue: WaitQueue,
+    /// Synchronisation objects of the threads, indexed by the ids handed out at creation.
+    pub mutex_list: Vec<Arc<dyn Mutex>>,
+    pub semaphore_list: Vec<Arc<Semaphore>>,
+    pub condvar_list: Vec<Arc<Condvar>>,
+    /// Signals sent but not delivered yet.
+    pub signals: SignalFlags,
+    /// Signals whose delivery is postponed, shared by all threads.
+    pub signal_mask: SignalFlags,
+    pub signal_actions: [SignalAction; MAX_SIG + 1],
+    pub usage: CpuUsage,
+    /// What the children reaped so far have used, including their own reaped children.
+    pub children_usage: CpuUsage
+}
+
+impl ProcessControlBlockInner {
+    pub fn get_user_token(&self) -> usize {
+        self.memory_set.token()
+    }
+    pub fn is_zombie(&self) -> bool {
+        self.is_zombie
+    }
+    pub fn get_task(&self, tid: usize) -> Option<Arc<TaskControlBlock>> {
+        self.tasks.get(tid).cloned().flatten()
+    }
+    /// Threads that have not exited yet.
+    pub fn live_threads(&self) -> usize {
+        self.tasks.iter().flatten().filter(|task| !task.is_exited()).count()
+    }
+}
+
+impl ProcessControlBlock {
+    fn with_memory_set(memory_set: MemorySet, heap_bottom: usize, parent: Option<Weak<Self>>,
+                       cspace: CSpace) -> Arc<Self> {
+        let process = Arc::new(Self {
+            pid: pid_alloc(),
+            inner: SpinLock::new(ProcessControlBlockInner {
+                is_exiting: false,
+                is_zombie: false,
+                memory_set,
+                heap_bottom,
+                program_brk: heap_bottom,
+                parent,
+                children: Vec::new(),
+                wait_queue: WaitQueue::new(),
+                cspace,
+                exit_code: 0,
+                tasks: Vec::new(),
+                tid_allocator: RecycleAllocator::new(0),
+                thread_wait_queue: WaitQueue::new(),
+                mutex_list: Vec::new(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                signals: SignalFlags::empty(),
+                signal_mask: SignalFlags::empty(),
+                signal_actions: [SignalAction::default(); MAX_SIG + 1],
+                usage: CpuUsage::default(),
+                children_usage: CpuUsage::default()
+            })
+        });
+        insert_into_pid2process(process.getpid(), &process);
+        process
+    }
+    /// Adds a thread to the process and returns it. Its trap context is left for the
+    /// caller to fill in. Returns None if there is no room for another user stack.
+    pub fn add_thread(self: &Arc<Self>) -> Option<Arc<TaskControlBlock>> {
+        let tid = self.inner_exclusive_access().tid_allocator.alloc();
+        let Some(res) = TaskUserRes::new(self, tid) else {
+            self.inner_exclusive_access().tid_allocator.dealloc(tid);
+            return None;
+        };
+        let task = Arc::new(TaskControlBlock::new(self, res));
+        let mut inner = self.inner_exclusive_access();
+        // Another hart is ending the process, which would not stop this thread.
+        if inner.is_exiting {
+            inner.tid_allocator.dealloc(tid);
+            return None;
+        }
+        if inner.tasks.len() <= tid {
+            inner.tasks.resize(tid + 1, None);
+        }
+        inner.tasks[tid] = Some(task.clone());
+        Some(task)
+    }
+    pub fn new(elf_data: &[u8]) -> Arc<Self> {
+        let (memory_set, user_sp, heap_bottom, entry_point) = MemorySet::from_elf(elf_data);
+        // The first process may hand out IRQ handlers to user-space drivers. It also gets the
+        // endpoint of the name server it starts, which every later process inherits by fork.
+        let mut cspace = CSpace::new();
+        cspace.insert_at(CAP_IRQ_CONTROL, Capability::new(CapObject::IrqControl));
+        let name_server = Arc::new(Endpoint::new());
+        cspace.insert_at(CAP_NAME_SERVER, Capability::new(CapObject::Endpoint(name_server)));
+        let process = Self::with_memory_set(memory_set, heap_bottom, None, cspace);
+        let task = process.add_thread().unwrap();
+        *task.inner_exclusive_access().get_trap_cx() = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            task.kernal_stack.get_top(),
+            trap_handler as *const () as usize
+        );
+        add_task(task);
+        process
+    }
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, ProcessControlBlockInner> {
+        self.inner.exclusive_access()
+    }
+    pub fn getpid(&self) -> usize { self.pid.0 }
+    /// Moves the program break to `new_brk`. Returns false if the heap cannot be resized.
+    pub fn change_program_brk(&self, new_brk: usize) -> bool {
+        let mut inner = self.inner_exclusive_access();
+        if new_brk < inner.heap_bottom {
+            return false;
+        }
+        let heap_start = VirtAddr::from(inner.heap_bottom).floor();
+        if !inner.memory_set.resize_area(heap_start, VirtAddr::from(new_brk).ceil()) {
+            return false;
+        }
+        inner.program_brk = new_brk;
+        true
+    }
+    /// Puts a capability to this process's own address space into its well-known slot.
+    pub fn grant_address_space(self: &Arc<Self>) {
+        let mut inner = self.inner_exclusive_access();
+        inner.cspace.remove(CAP_ADDRESS_SPACE);
+        inner.cspace.insert_at(
+            CAP_ADDRESS_SPACE, Capability::new(CapObject::AddressSpace(Arc::downgrade(self)))
+        );
+    }
+    /// Copies a process with a single live thread, which is then always the first one. The
+    /// child's only thread resumes where the parent's does.
+    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+        let mut parent_inner = self.inner_exclusive_access();
+        assert_eq!(parent_inner.live_threads(), 1);
+        let memory_set = MemorySet::from_existed_user(&mut parent_inner.memory_set);
+        let child = Self::with_memory_set(
+            memory_set,
+            parent_inner.heap_bottom,
+            Some(Arc::downgrade(self)),
+            parent_inner.cspace.derive_all()
+        );
+        let mut child_inner = child.inner_exclusive_access();
+        child_inner.program_brk = parent_inner.program_brk;
+        child_inner.signal_mask = parent_inner.signal_mask;
+        child_inner.signal_actions = parent_inner.signal_actions;
+        drop(child_inner);
+        parent_inner.children.push(child.clone());
+        let sched = parent_inner.get_task(0).unwrap().inner_exclusive_access().sched.inherit();
+        drop(parent_inner);
+        child.grant_address_space();
+        let task = child.add_thread().unwrap();
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.get_trap_cx().kernel_sp = task.kernal_stack.get_top();
+        task_inner.sched = sched;
+        drop(task_inner);
+        add_task(task);
+        child
+    }
+    /// Replaces the image of a single-threaded process.
+    pub fn exec(&self, elf_data: &[u8]) {
+        let (memory_set,  user_sp, heap_bottom, entry_point) = MemorySet::from_elf(elf_data);
+        let mut inner = self.inner_exclusive_access();
+        assert_eq!(inner.live_threads(), 1);
+        inner.memory_set = memory_set;
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        // Handlers are gone with the old image, and so are the signals pending for them.
+        // Ignored signals stay ignored.
+        for signum in 1..=MAX_SIG {
+            let handler = inner.signal_actions[signum].handler;
+            if handler > SIG_IGN {
+                inner.signals.remove(SignalFlags::from_signum(signum));
+            }
+            if handler != SIG_IGN {
+                inner.signal_actions[signum] = SignalAction::default();
+            }
+        }
+        let task = inner.get_task(0).unwrap();
+        let trap_cx_ppn = inner.memory_set.translate(VirtAddr::from(trap_context_position(0)).into())
+            .unwrap()
+            .ppn();
+        // A handler interrupted by exec never returns, so its mask goes as well.
+        if let Some(frame) = task.inner_exclusive_access().signal_frame.take() {
+            inner.signal_mask = frame.mask;
+        }
+        drop(inner);
+        let mut task_inner = task.inner_exclusive_access();
+        task_inner.trap_cx_ppn = trap_cx_ppn;
+        *task_inner.get_trap_cx() = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.exclusive_access().token(),
+            task.kernal_stack.get_top(),
+            trap_handler as *const () as usize
+        )
+    }
+}
+
@@ -1,34 +1,79 @@
 use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use lazy_static::lazy_static;
-use crate::sync::up::UPSafeCell;
+use crate::config::CPUS;
+use crate::sync::spin::SpinLock;
 use crate::task::context::TaskContext;
 use crate::task::manager::fetch_task;
 use crate::task::switch::__switch;
-use crate::task::task::ProcessControlBlock;
+use crate::task::process::ProcessControlBlock;
+use crate::task::reap_thread;
+use crate::task::task::{TaskControlBlock, TaskStatus};
+use crate::task::usage::{charge_kernel_time, stamp};
+use crate::timer::get_time_us;
+use crate::trap::ipi::handle_ipi;
+use crate::trap::poll_interrupts;
 use crate::trap::context::TrapContext;
 
 pub struct Processor {
-    current: Option<Arc<ProcessControlBlock>>,
-    idle_task_cx: TaskContext
+    current: Option<Arc<TaskControlBlock>>,
+    idle_task_cx: TaskContext,
+    /// Microseconds spent waiting in `idle`.
+    idle_time: usize,
+    stat: SchedStat
+}
+
+/// Scheduling counters of a hart, in the layout `get_sched_stat` hands to user space.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SchedStat {
+    /// Threads the hart switched to.
+    pub switches: usize,
+    /// Switches to a thread that ran on another hart last.
+    pub migrations: usize
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: UPSafeCell<Processor> = unsafe {
-        UPSafeCell::new(Processor::new())
-    };
+    /// Indexed by hart id. Only the hart itself uses its processor, apart from `idle_time`
+    /// and `stat`.
+    static ref PROCESSORS: Vec<SpinLock<Processor>> =
+        (0..CPUS).map(|_| SpinLock::new(Processor::new())).collect();
+}
+
+/// Harts that have started scheduling, as a bit mask.
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+/// Harts waiting in `idle`, as a bit mask.
+static IDLE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn online_harts() -> usize {
+    ONLINE_HARTS.load(Ordering::SeqCst)
+}
+
+pub fn idle_harts() -> usize {
+    IDLE_HARTS.load(Ordering::SeqCst)
+}
+
+/// The processor of the hart running this code.
+fn processor() -> &'static SpinLock<Processor> {
+    &PROCESSORS[current_hart()]
 }
 
 impl Processor {
     pub fn new() -> Self {
         Self {
             current: None,
-            idle_task_cx: TaskContext::zero_init()
+            idle_task_cx: TaskContext::zero_init(),
+            idle_time: 0,
+            stat: SchedStat::default()
         }
     }
-    pub fn take_current(&mut self) -> Option<Arc<ProcessControlBlock>> {
+    pub fn take_current(&mut self) -> Option<Arc<TaskControlBlock>> {
         self.current.take()
     }
-    pub fn current(&self) -> Option<Arc<ProcessControlBlock>> {
+    pub fn current(&self) -> Option<Arc<TaskControlBlock>> {
         self.current.as_ref().map(|task| task.clone())
     }
     pub fn get_idle_task_cx_ptr(&mut self) -> *mut TaskContext {
@@ -36,42 +81,123 @@ impl Processor {
     }
 }
 
-pub fn task_current_task() -> Option<Arc<ProcessControlBlock>> {
-    PROCESSOR.exclusive_access().take_current()
+/// Waits for an interrupt while nothing is runnable. Interrupts stay masked in the kernel,
+/// but `wfi` still returns as soon as an enabled one is pending, so an interrupt that
+/// arrives after the ready queues were found empty cannot be missed. It is handled by
+/// polling once the hart wakes up.
+/// Other harts wake an idle hart up when they queue a thread it could run. The hart is
+/// marked idle before it looks at the queues a last time, so a thread is either found
+/// then or queued late enough for its hart to see the mark. Returns such a thread.
+fn idle() -> Option<Arc<TaskControlBlock>> {
+    let hart = current_hart();
+    IDLE_HARTS.fetch_or(1 << hart, Ordering::SeqCst);
+    if let Some(task) = fetch_task() {
+        IDLE_HARTS.fetch_and(!(1 << hart), Ordering::SeqCst);
+        return Some(task);
+    }
+    let start = get_time_us();
+    unsafe { asm!("wfi"); }
+    IDLE_HARTS.fetch_and(!(1 << hart), Ordering::SeqCst);
+    processor().exclusive_access().idle_time += get_time_us() - start;
+    poll_interrupts();
+    None
 }
 
-pub fn current_task() -> Option<Arc<ProcessControlBlock>> {
-    PROCESSOR.exclusive_access().current()
+/// Whether `hart` exists and has started scheduling.
+fn is_online(hart: usize) -> bool {
+    hart < CPUS && online_harts() & (1 << hart) != 0
+}
+
+/// Microseconds `hart` has spent idle since boot, or None if there is no such hart.
+pub fn idle_time(hart: usize) -> Option<usize> {
+    if !is_online(hart) {
+        return None;
+    }
+    Some(PROCESSORS[hart].exclusive_access().idle_time)
+}
+
+/// Scheduling counters of `hart` since boot, or None if there is no such hart.
+pub fn sched_stat(hart: usize) -> Option<SchedStat> {
+    if !is_online(hart) {
+        return None;
+    }
+    Some(PROCESSORS[hart].exclusive_access().stat)
+}
+
+/// Id of the hart running this code. The kernel keeps it in `tp`, which `sbi_entry` sets up
+/// and the trap entry restores after user code.
+pub fn current_hart() -> usize {
+    let hart;
+    unsafe { asm!("mv {}, tp", out(reg) hart); }
+    hart
+}
+
+pub fn task_current_task() -> Option<Arc<TaskControlBlock>> {
+    processor().exclusive_access().take_current()
+}
+
+pub fn current_task() -> Option<Arc<TaskControlBlock>> {
+    processor().exclusive_access().current()
+}
+
+pub fn current_process() -> Arc<ProcessControlBlock> {
+    current_task().unwrap().process()
 }
 
 pub fn current_trap_cx() -> &'static mut TrapContext {
     current_task().unwrap().inner_exclusive_access().get_trap_cx()
 }
 
+/// Where the current thread's trap context is mapped in user space.
+pub fn current_trap_cx_user_va() -> usize {
+    current_task().unwrap().inner_exclusive_access().res.as_ref().unwrap().trap_cx_user_va()
+}
+
 pub fn current_user_token() -> usize {
-    let task = current_task().unwrap();
-    task.inner_exclusive_access().get_user_token()
+    current_task().unwrap().get_user_token()
 }
 
 pub fn run_tasks() {
+    let hart = current_hart();
+    ONLINE_HARTS.fetch_or(1 << hart, Ordering::SeqCst);
     loop {
-        let mut processor = PROCESSOR.exclusive_access();
-        if let Some(task) = fetch_task() {
-            let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
-            let mut task_inner = task.inner_exclusive_access();
-            let next_task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
-            drop(task_inner);
-            processor.current = Some(task);
-            drop(processor);
-            unsafe {
-                __switch(idle_task_cx_ptr, next_task_cx_ptr);
-            }
+        let Some(task) = fetch_task().or_else(idle) else {
+            continue;
+        };
+        let mut task_inner = task.inner_exclusive_access();
+        if task_inner.task_status == TaskStatus::Exited {
+            continue;
+        }
+        task_inner.task_status = TaskStatus::Running;
+        let migrated = task_inner.sched.last_hart.replace(hart).is_some_and(|last| last != hart);
+        let next_task_cx_ptr = &mut task_inner.task_cx as *mut TaskContext;
+        drop(task_inner);
+        // The hart that ran the task last may still be switching away from its stack.
+        while task.on_cpu.swap(true, Ordering::SeqCst) {
+            handle_ipi();
+            spin_loop();
+        }
+        stamp(&task);
+        let mut processor = processor().exclusive_access();
+        let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
+        processor.current = Some(task);
+        processor.stat.switches += 1;
+        processor.stat.migrations += migrated as usize;
+        drop(processor);
+        unsafe {
+            __switch(idle_task_cx_ptr, next_task_cx_ptr);
+        }
+        let task = task_current_task().unwrap();
+        task.on_cpu.store(false, Ordering::SeqCst);
+        if task.is_exited() {
+            reap_thread(&task);
         }
     }
 }
 
 pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
-    let mut processor = PROCESSOR.exclusive_access();
+    charge_kernel_time();
+    let mut processor = processor().exclusive_access();
     let idle_task_cx_ptr = processor.get_idle_task_cx_ptr();
     drop(processor);
     unsafe {
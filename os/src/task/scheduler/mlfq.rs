@@ -0,0 +1,93 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use crate::task::scheduler::Scheduler;
+use crate::task::task::TaskControlBlock;
+
+const LEVELS: usize = 4;
+/// Ticks after which every thread moves back to the top queue, so that threads demoted for
+/// being busy once cannot starve.
+const BOOST_PERIOD: usize = 100;
+
+/// Multi-level feedback queue. Threads start in the top queue and move one queue down each
+/// time they use up the time slice of their queue, which doubles with every level. Threads
+/// that block or yield early stay where they are, so interactive threads keep precedence
+/// over busy ones.
+pub struct MlfqScheduler {
+    queues: [VecDeque<Arc<TaskControlBlock>>; LEVELS],
+    ticks: usize,
+    /// Number of priority boosts so far.
+    epoch: usize
+}
+
+/// The queue a thread is in, the ticks it has used there and the last boost it has seen.
+#[derive(Copy, Clone, Default)]
+pub struct MlfqEntity {
+    level: usize,
+    ticks: usize,
+    epoch: usize
+}
+
+fn time_slice(level: usize) -> usize {
+    1 << level
+}
+
+impl Scheduler for MlfqScheduler {
+    const USES_PRIORITY: bool = false;
+    type Entity = MlfqEntity;
+    fn new() -> Self {
+        Self {
+            queues: Default::default(),
+            ticks: 0,
+            epoch: 0
+        }
+    }
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let mut inner = task.inner_exclusive_access();
+        if inner.sched.policy.epoch != self.epoch {
+            inner.sched.policy.epoch = self.epoch;
+            inner.sched.policy.level = 0;
+            inner.sched.policy.ticks = 0;
+        }
+        let level = inner.sched.policy.level;
+        drop(inner);
+        self.queues[level].push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        let len = self.len();
+        for queue in self.queues.iter_mut() {
+            queue.retain(|queued| !Arc::ptr_eq(queued, task));
+        }
+        self.len() != len
+    }
+    fn len(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+    /// Takes the thread queued last in the lowest queue.
+    fn steal(&mut self, hart: usize) -> Option<Arc<TaskControlBlock>> {
+        self.queues.iter_mut().rev().find_map(|queue| {
+            let idx = queue.iter().rposition(|task| task.may_run_on(hart))?;
+            queue.remove(idx)
+        })
+    }
+    fn tick(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(BOOST_PERIOD) {
+            self.epoch += 1;
+            let queued: VecDeque<_> = self.queues.iter_mut().flat_map(|queue| queue.drain(..)).collect();
+            for task in queued {
+                self.add(task);
+            }
+        }
+        let mut inner = task.inner_exclusive_access();
+        inner.sched.policy.ticks += 1;
+        if inner.sched.policy.ticks < time_slice(inner.sched.policy.level) {
+            return false;
+        }
+        inner.sched.policy.ticks = 0;
+        inner.sched.policy.level = (inner.sched.policy.level + 1).min(LEVELS - 1);
+        true
+    }
+}
@@ -0,0 +1,106 @@
+//! Scheduling policies behind `add_task` and `fetch_task`. The policy is picked at build time
+//! with one of the `sched-rr`, `sched-stride` and `sched-mlfq` cargo features. Every hart has
+//! a ready queue of its own, run by its own instance of the policy.
+
+use alloc::sync::Arc;
+use crate::config::CPUS;
+use crate::task::task::TaskControlBlock;
+
+#[cfg(feature = "sched-mlfq")]
+mod mlfq;
+#[cfg(feature = "sched-rr")]
+mod rr;
+#[cfg(feature = "sched-stride")]
+mod stride;
+
+#[cfg(feature = "sched-mlfq")]
+pub use mlfq::MlfqScheduler as Policy;
+#[cfg(feature = "sched-rr")]
+pub use rr::RoundRobinScheduler as Policy;
+#[cfg(feature = "sched-stride")]
+pub use stride::StrideScheduler as Policy;
+
+#[cfg(not(any(feature = "sched-rr", feature = "sched-stride", feature = "sched-mlfq")))]
+compile_error!("pick a scheduling policy with one of the sched-* features");
+#[cfg(any(
+    all(feature = "sched-rr", feature = "sched-stride"),
+    all(feature = "sched-rr", feature = "sched-mlfq"),
+    all(feature = "sched-stride", feature = "sched-mlfq")
+))]
+compile_error!("only one of the sched-* features can be enabled");
+
+pub trait Scheduler {
+    /// Whether `set_priority` and `nice` have any effect.
+    const USES_PRIORITY: bool;
+    /// What the policy keeps per thread, in `SchedEntity::policy`.
+    type Entity: Copy + Default;
+    fn new() -> Self;
+    fn add(&mut self, task: Arc<TaskControlBlock>);
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>>;
+    /// Returns whether `task` was queued.
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) -> bool;
+    /// Number of queued threads.
+    fn len(&self) -> usize;
+    /// Gives up a queued thread that may run on `hart`, preferably one that would not run
+    /// here soon anyway.
+    fn steal(&mut self, hart: usize) -> Option<Arc<TaskControlBlock>>;
+    /// Called on every timer tick that interrupts `task`. Returns whether to preempt it.
+    fn tick(&mut self, _task: &Arc<TaskControlBlock>) -> bool {
+        true
+    }
+}
+
+pub const MIN_NICE: isize = -20;
+pub const MAX_NICE: isize = 19;
+/// Priority of a thread whose nice value is 0.
+pub const DEFAULT_PRIORITY: usize = 1024;
+/// Affinity of a thread that may run on every hart.
+pub const ALL_HARTS: usize = (1 << CPUS) - 1;
+
+/// Priorities of the nice values from -20 to 19, the same as Linux uses. Every step changes
+/// the share of the CPU by roughly 10%.
+const NICE_TO_PRIORITY: [usize; (MAX_NICE - MIN_NICE + 1) as usize] = [
+    88761, 71755, 56483, 46273, 36291,
+    29154, 23254, 18705, 14949, 11916,
+    9548, 7620, 6100, 4904, 3906,
+    3121, 2501, 1991, 1586, 1277,
+    1024, 820, 655, 526, 423,
+    335, 272, 215, 172, 137,
+    110, 87, 70, 56, 45,
+    36, 29, 23, 18, 15
+];
+
+/// Scheduling state of a thread.
+#[derive(Copy, Clone)]
+pub struct SchedEntity {
+    /// Weight of the thread, its share of the CPU is proportional to it under stride
+    /// scheduling.
+    pub priority: usize,
+    pub nice: isize,
+    /// Harts the thread may run on, as a bit mask.
+    pub affinity: usize,
+    /// Hart the thread ran on last, whose ready queue it goes back to.
+    pub last_hart: Option<usize>,
+    pub policy: <Policy as Scheduler>::Entity
+}
+
+impl SchedEntity {
+    pub fn new() -> Self {
+        Self {
+            priority: DEFAULT_PRIORITY,
+            nice: 0,
+            affinity: ALL_HARTS,
+            last_hart: None,
+            policy: Default::default()
+        }
+    }
+    /// State for a thread that `self`'s thread creates, which keeps its priority and
+    /// affinity.
+    pub fn inherit(&self) -> Self {
+        Self { priority: self.priority, nice: self.nice, affinity: self.affinity, ..Self::new() }
+    }
+    pub fn set_nice(&mut self, nice: isize) {
+        self.nice = nice.clamp(MIN_NICE, MAX_NICE);
+        self.priority = NICE_TO_PRIORITY[(self.nice - MIN_NICE) as usize];
+    }
+}
@@ -0,0 +1,38 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use crate::task::scheduler::Scheduler;
+use crate::task::task::TaskControlBlock;
+
+/// Runs ready threads in turn, one tick each.
+pub struct RoundRobinScheduler {
+    ready_queue: VecDeque<Arc<TaskControlBlock>>
+}
+
+impl Scheduler for RoundRobinScheduler {
+    const USES_PRIORITY: bool = false;
+    type Entity = ();
+    fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new()
+        }
+    }
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        self.ready_queue.push_back(task);
+    }
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        self.ready_queue.pop_front()
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        let len = self.ready_queue.len();
+        self.ready_queue.retain(|queued| !Arc::ptr_eq(queued, task));
+        self.ready_queue.len() != len
+    }
+    fn len(&self) -> usize {
+        self.ready_queue.len()
+    }
+    /// Takes the thread queued last.
+    fn steal(&mut self, hart: usize) -> Option<Arc<TaskControlBlock>> {
+        let idx = self.ready_queue.iter().rposition(|task| task.may_run_on(hart))?;
+        self.ready_queue.remove(idx)
+    }
+}
@@ -0,0 +1,110 @@
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use crate::task::scheduler::Scheduler;
+use crate::task::task::TaskControlBlock;
+
+/// Pass added per tick for a thread with priority 1.
+const BIG_STRIDE: u64 = 1 << 40;
+
+/// How much CPU time a thread has had, scaled by its priority.
+#[derive(Copy, Clone, Default)]
+pub struct StrideEntity {
+    pass: u64,
+    /// Set while a stolen thread moves to another hart: how far its pass is ahead of the
+    /// present of the hart it left. Every hart keeps its own present.
+    lead: Option<u64>
+}
+
+/// A ready thread and its pass when it was queued.
+struct Stride {
+    pass: u64,
+    task: Arc<TaskControlBlock>
+}
+
+impl PartialEq for Stride {
+    fn eq(&self, other: &Self) -> bool {
+        self.pass == other.pass
+    }
+}
+
+impl Eq for Stride {}
+
+impl PartialOrd for Stride {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Stride {
+    /// Reversed so that the `BinaryHeap` pops the smallest pass first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.pass.cmp(&self.pass)
+    }
+}
+
+/// Always runs the thread with the smallest pass, and advances its pass by a stride
+/// inversely proportional to its priority. Over time every thread gets a share of the CPU
+/// proportional to its priority.
+pub struct StrideScheduler {
+    ready_queue: BinaryHeap<Stride>,
+    /// Pass of the thread fetched last, the scheduler's notion of the present.
+    min_pass: u64
+}
+
+impl Scheduler for StrideScheduler {
+    const USES_PRIORITY: bool = true;
+    type Entity = StrideEntity;
+    fn new() -> Self {
+        Self {
+            ready_queue: BinaryHeap::new(),
+            min_pass: 0
+        }
+    }
+    /// A thread that was new or blocked for a long time starts at the present pass, rather
+    /// than making up for the time it did not want the CPU. A stolen thread keeps its lead.
+    fn add(&mut self, task: Arc<TaskControlBlock>) {
+        let mut inner = task.inner_exclusive_access();
+        let policy = &mut inner.sched.policy;
+        policy.pass = match policy.lead.take() {
+            Some(lead) => self.min_pass + lead,
+            None => policy.pass.max(self.min_pass)
+        };
+        let pass = inner.sched.policy.pass;
+        drop(inner);
+        self.ready_queue.push(Stride { pass, task });
+    }
+    fn fetch(&mut self) -> Option<Arc<TaskControlBlock>> {
+        let Stride { pass, task } = self.ready_queue.pop()?;
+        self.min_pass = pass;
+        let mut inner = task.inner_exclusive_access();
+        inner.sched.policy.pass = pass + BIG_STRIDE / inner.sched.priority as u64;
+        drop(inner);
+        Some(task)
+    }
+    fn remove(&mut self, task: &Arc<TaskControlBlock>) -> bool {
+        let len = self.ready_queue.len();
+        self.ready_queue.retain(|queued| !Arc::ptr_eq(&queued.task, task));
+        self.ready_queue.len() != len
+    }
+    fn len(&self) -> usize {
+        self.ready_queue.len()
+    }
+    /// Takes the thread with the largest pass, which is charged for the run ahead like in
+    /// `fetch` and rebased onto the present of the hart it moves to once it is added there.
+    fn steal(&mut self, hart: usize) -> Option<Arc<TaskControlBlock>> {
+        let mut queued = core::mem::take(&mut self.ready_queue).into_vec();
+        let idx = queued.iter().enumerate()
+            .filter(|(_, queued)| queued.task.may_run_on(hart))
+            .max_by_key(|(_, queued)| queued.pass)
+            .map(|(idx, _)| idx);
+        let stolen = idx.map(|idx| queued.swap_remove(idx));
+        self.ready_queue = queued.into();
+        let Stride { pass, task } = stolen?;
+        let mut inner = task.inner_exclusive_access();
+        let stride = BIG_STRIDE / inner.sched.priority as u64;
+        inner.sched.policy.lead = Some(pass.saturating_sub(self.min_pass) + stride);
+        drop(inner);
+        Some(task)
+    }
+}
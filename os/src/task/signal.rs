@@ -0,0 +1,158 @@
+use alloc::sync::Arc;
+use bitflags::bitflags;
+use crate::task::{exit_process, exit_process_and_run_next};
+use crate::task::process::ProcessControlBlock;
+use crate::task::processor::current_task;
+use crate::trap::context::TrapContext;
+
+pub const MAX_SIG: usize = 31;
+
+pub const SIGINT: usize = 2;
+pub const SIGILL: usize = 4;
+pub const SIGKILL: usize = 9;
+pub const SIGSEGV: usize = 11;
+pub const SIGCHLD: usize = 17;
+
+/// Handler values with a special meaning.
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+bitflags! {
+    /// A set of signals, bit `n` standing for signal `n` like in Linux.
+    #[derive(Copy, Clone, PartialEq, Default)]
+    pub struct SignalFlags: u32 {
+        const SIGHUP = 1 << 1;
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGILL = 1 << 4;
+        const SIGTRAP = 1 << 5;
+        const SIGABRT = 1 << 6;
+        const SIGBUS = 1 << 7;
+        const SIGFPE = 1 << 8;
+        const SIGKILL = 1 << 9;
+        const SIGUSR1 = 1 << 10;
+        const SIGSEGV = 1 << 11;
+        const SIGUSR2 = 1 << 12;
+        const SIGPIPE = 1 << 13;
+        const SIGALRM = 1 << 14;
+        const SIGTERM = 1 << 15;
+        const SIGCHLD = 1 << 17;
+        const _ = !0;
+    }
+}
+
+impl SignalFlags {
+    pub fn from_signum(signum: usize) -> Self {
+        Self::from_bits_retain(1 << signum)
+    }
+}
+
+/// What to do with a signal, as passed to `sigaction`. The handler is called with the signal
+/// number and returns to `restorer`, which has to call `sigreturn`. `mask` is blocked on top
+/// of the signal itself while the handler runs.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub restorer: usize,
+    pub mask: SignalFlags
+}
+
+/// What a thread was doing before it was sent into a signal handler.
+pub struct SignalFrame {
+    pub trap_cx: TrapContext,
+    pub mask: SignalFlags
+}
+
+/// Exit code of a process that a signal terminated.
+pub fn killed_by(signum: usize) -> i32 {
+    -(signum as i32)
+}
+
+/// Signals whose default action is to do nothing, the others terminate the process.
+fn ignored_by_default(signum: usize) -> bool {
+    signum == SIGCHLD
+}
+
+/// Whether `signum` terminates `process` once it is delivered, now or after being unmasked.
+pub fn is_fatal(process: &ProcessControlBlock, signum: usize) -> bool {
+    signum == SIGKILL || process.inner_exclusive_access().signal_actions[signum].handler == SIG_DFL
+        && !ignored_by_default(signum)
+}
+
+/// Whether delivering `signum` terminates `process`.
+fn terminates(process: &ProcessControlBlock, signum: usize) -> bool {
+    is_fatal(process, signum)
+        && !process.inner_exclusive_access().signal_mask.contains(SignalFlags::from_signum(signum))
+}
+
+/// Makes `signum` pending for `process`. A signal that terminates the process takes effect
+/// at once, so that it also stops processes whose threads are all blocked. Other signals wait
+/// until a thread returns to user mode, which is also when the current process is
+/// terminated.
+pub fn send_signal(process: &Arc<ProcessControlBlock>, signum: usize) {
+    let is_current = current_task().is_some_and(|task| Arc::ptr_eq(&task.process(), process));
+    if is_current || !terminates(process, signum) {
+        process.inner_exclusive_access().signals |= SignalFlags::from_signum(signum);
+    } else if !process.inner_exclusive_access().is_zombie() {
+        exit_process(process, killed_by(signum));
+    }
+}
+
+/// Queues the signal for a fault of the current thread if the process has a handler that
+/// can run right away. Returns false if the fault has to terminate the process instead,
+/// since ignoring it would only repeat the faulting instruction.
+pub fn catch_fault(signum: usize) -> bool {
+    let task = current_task().unwrap();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let flag = SignalFlags::from_signum(signum);
+    let caught = inner.signal_actions[signum].handler > SIG_IGN
+        && !inner.signal_mask.contains(flag)
+        && task.inner_exclusive_access().signal_frame.is_none();
+    if caught {
+        inner.signals |= flag;
+    }
+    caught
+}
+
+/// Acts on the pending, unmasked signals of the current process before its thread returns
+/// to user mode. At most one handler runs at a time per thread; the signals it would
+/// otherwise take stay pending until it calls `sigreturn`.
+pub fn handle_signals() {
+    let task = current_task().unwrap();
+    let process = task.process();
+    let mut inner = process.inner_exclusive_access();
+    let pending = inner.signals & !inner.signal_mask;
+    for signum in 1..=MAX_SIG {
+        let flag = SignalFlags::from_signum(signum);
+        if !pending.contains(flag) {
+            continue;
+        }
+        let action = inner.signal_actions[signum];
+        if signum == SIGKILL || action.handler == SIG_DFL || action.handler == SIG_IGN {
+            inner.signals.remove(flag);
+            if signum != SIGKILL && (action.handler == SIG_IGN || ignored_by_default(signum)) {
+                continue;
+            }
+            drop(inner);
+            drop(process);
+            drop(task);
+            exit_process_and_run_next(killed_by(signum));
+            unreachable!();
+        }
+        let mut task_inner = task.inner_exclusive_access();
+        if task_inner.signal_frame.is_some() {
+            continue;
+        }
+        inner.signals.remove(flag);
+        let trap_cx = task_inner.get_trap_cx();
+        task_inner.signal_frame = Some(SignalFrame { trap_cx: trap_cx.clone(), mask: inner.signal_mask });
+        inner.signal_mask |= action.mask | flag;
+        inner.signal_mask.remove(SignalFlags::SIGKILL);
+        trap_cx.sepc = action.handler;
+        trap_cx.reg[1] = action.restorer;
+        trap_cx.reg[10] = signum;
+        return;
+    }
+}
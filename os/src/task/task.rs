@@ -1,136 +1,154 @@
-use crate::config::TRAP_CONTEXT;
-use crate::mem::address::{PhysPageNum, VirtAddr};
-use crate::mem::memory_set::{MemorySet, KERNEL_SPACE};
-use crate::sync::up::UPSafeCell;
+use crate::config::{trap_context_position, THREAD_STACK_SIZE};
+use crate::ipc::IpcState;
+use crate::mem::address::{PhysPageNum, VirtAddr, VirtPageNum, PAGE_SIZE};
+use crate::mem::memory_set::MapPermission;
+use crate::sync::spin::{SpinLock, SpinLockGuard};
 use crate::task::context::TaskContext;
-use crate::task::pid::{pid_alloc, KernalStack, PidHandle};
+use crate::task::pid::KernalStack;
+use crate::task::process::ProcessControlBlock;
+use crate::task::scheduler::SchedEntity;
+use crate::task::signal::SignalFrame;
 use crate::trap::context::TrapContext;
-use crate::trap::trap_handler;
-use alloc::sync::Arc;
-use alloc::sync::Weak;
-use alloc::vec::Vec;
-use core::cell::RefMut;
+use alloc::sync::{Arc, Weak};
+use core::sync::atomic::AtomicBool;
 
 #[derive(Copy, Clone, PartialEq)]
 pub enum TaskStatus {
     Ready,
     Running,
-    Zombie,
+    Blocked,
+    /// The thread, or the whole process, has exited. It is never scheduled again.
+    Exited,
 }
 
-pub struct ProcessControlBlock {
-    pub pid: PidHandle,
+/// A thread. Threads of a process share its address space and capabilities, each has its
+/// own kernel stack, trap context page and user stack.
+pub struct TaskControlBlock {
+    pub process: Weak<ProcessControlBlock>,
+    pub tid: usize,
     pub kernal_stack: KernalStack,
-    inner: UPSafeCell<ProcessControlBlockInner>,
+    /// Set while a hart runs the thread or is still switching away from it, so that no
+    /// other hart switches to its kernel stack in the meantime.
+    pub on_cpu: AtomicBool,
+    inner: SpinLock<TaskControlBlockInner>,
 }
 
-pub struct ProcessControlBlockInner {
+pub struct TaskControlBlockInner {
     pub task_status: TaskStatus,
     pub task_cx: TaskContext,
-    pub memory_set: MemorySet,
+    /// Freed as soon as the thread exits, while the control block lives on until joined.
+    pub res: Option<TaskUserRes>,
     pub trap_cx_ppn: PhysPageNum,
-    pub base_size: usize,
-    pub parent: Option<Weak<ProcessControlBlock>>,
-    pub children: Vec<Arc<ProcessControlBlock>>,
-    pub exit_code: i32
+    pub ipc: IpcState,
+    /// Set once the thread has exited.
+    pub exit_code: Option<i32>,
+    /// Set while the thread runs a signal handler, see `signal::handle_signals`.
+    pub signal_frame: Option<SignalFrame>,
+    pub sched: SchedEntity,
+    /// When the thread's CPU time was last charged, see `usage`.
+    pub cpu_stamp: usize
 }
 
-impl ProcessControlBlockInner {
-    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
-        self.trap_cx_ppn.get_mut()
+/// The parts of a thread's user address space that belong to it alone.
+pub struct TaskUserRes {
+    pub tid: usize,
+    /// Start of the user stack, None for the first thread whose stack comes with the ELF.
+    ustack_bottom: Option<VirtPageNum>,
+    process: Weak<ProcessControlBlock>
+}
+
+impl TaskUserRes {
+    /// Maps the trap context page and, unless `tid` is 0, a user stack for a new thread.
+    /// The first thread's trap context is mapped together with the rest of the ELF.
+    pub fn new(process: &Arc<ProcessControlBlock>, tid: usize) -> Option<Self> {
+        if tid == 0 {
+            return Some(Self { tid, ustack_bottom: None, process: Arc::downgrade(process) });
+        }
+        let mut inner = process.inner_exclusive_access();
+        let pages = THREAD_STACK_SIZE / PAGE_SIZE;
+        let ustack_bottom = inner.memory_set.find_free_area(pages)?;
+        let permission = MapPermission::R | MapPermission::W | MapPermission::U;
+        if !inner.memory_set.map_thread_stack(tid, ustack_bottom, VirtPageNum(ustack_bottom.0 + pages), permission) {
+            return None;
+        }
+        let trap_cx_bottom = trap_context_position(tid);
+        inner.memory_set.insert_framed_data(
+            trap_cx_bottom.into(),
+            (trap_cx_bottom + PAGE_SIZE).into(),
+            MapPermission::R | MapPermission::W
+        );
+        Some(Self { tid, ustack_bottom: Some(ustack_bottom), process: Arc::downgrade(process) })
     }
-    pub fn get_user_token(&self) -> usize {
-        self.memory_set.token()
+    pub fn ustack_top(&self) -> Option<usize> {
+        self.ustack_bottom.map(|bottom| usize::from(VirtAddr::from(bottom)) + THREAD_STACK_SIZE)
     }
-    pub fn get_status(&self) -> TaskStatus {
-        self.task_status
+    pub fn trap_cx_user_va(&self) -> usize {
+        trap_context_position(self.tid)
     }
-    pub fn is_zombie(&self) -> bool {
-        self.task_status == TaskStatus::Zombie
+    /// Takes the process's lock, so the thread's own lock must not be held.
+    pub fn trap_cx_ppn(&self) -> PhysPageNum {
+        let process = self.process.upgrade().unwrap();
+        let inner = process.inner_exclusive_access();
+        inner.memory_set.translate(VirtAddr::from(self.trap_cx_user_va()).into()).unwrap().ppn()
     }
 }
 
-impl ProcessControlBlock {
-    pub fn new(elf_data: &[u8]) -> Self {
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set
-            .translate(VirtAddr::from(TRAP_CONTEXT).into())
-            .unwrap()
-            .ppn();
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernalStack::new(&pid_handle);
-        let kstack_top = kernel_stack.get_top();
-        let ret = Self {
-            pid: pid_handle,
-            kernal_stack: kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(ProcessControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: user_sp,
-                    task_cx: TaskContext::goto_trap_return(kstack_top),
-                    task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: None,
-                    children: Vec::new(),
-                    exit_code: 0
-                })
-            }
+impl Drop for TaskUserRes {
+    fn drop(&mut self) {
+        if self.ustack_bottom.is_none() {
+            return;
+        }
+        let Some(process) = self.process.upgrade() else {
+            return;
         };
-        let trap_cx = ret.inner_exclusive_access().get_trap_cx();
-        *trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            kstack_top,
-            trap_handler as usize
-        );
-        ret
+        let mut inner = process.inner_exclusive_access();
+        inner.memory_set.unmap_thread_stack(self.tid);
+        inner.memory_set.remove_area_with_start_vpn(VirtAddr::from(self.trap_cx_user_va()).into());
     }
-    pub fn inner_exclusive_access(&self) -> RefMut<'_, ProcessControlBlockInner> {
+}
+
+impl TaskControlBlockInner {
+    pub fn get_trap_cx(&self) -> &'static mut TrapContext {
+        self.trap_cx_ppn.get_mut()
+    }
+}
+
+impl TaskControlBlock {
+    pub fn new(process: &Arc<ProcessControlBlock>, res: TaskUserRes) -> Self {
+        let kernal_stack = KernalStack::new();
+        let kstack_top = kernal_stack.get_top();
+        Self {
+            process: Arc::downgrade(process),
+            tid: res.tid,
+            kernal_stack,
+            on_cpu: AtomicBool::new(false),
+            inner: SpinLock::new(TaskControlBlockInner {
+                task_status: TaskStatus::Ready,
+                task_cx: TaskContext::goto_trap_return(kstack_top),
+                trap_cx_ppn: res.trap_cx_ppn(),
+                res: Some(res),
+                ipc: IpcState::default(),
+                exit_code: None,
+                signal_frame: None,
+                sched: SchedEntity::new(),
+                cpu_stamp: 0
+            })
+        }
+    }
+    pub fn inner_exclusive_access(&self) -> SpinLockGuard<'_, TaskControlBlockInner> {
         self.inner.exclusive_access()
     }
-    pub fn getpid(&self) -> usize { self.pid.0 }
-    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
-        let mut parent_inner = self.inner_exclusive_access();
-        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set);
-        let trap_cx_ppn = memory_set.translate(VirtAddr::from(TRAP_CONTEXT).into()).unwrap().ppn();
-        let pid_handle = pid_alloc();
-        let kernel_stack = KernalStack::new(&pid_handle);
-        let kstack_top = kernel_stack.get_top();
-        let ret = Arc::new(Self {
-            pid: pid_handle,
-            kernal_stack: kernel_stack,
-            inner: unsafe {
-                UPSafeCell::new(ProcessControlBlockInner {
-                    trap_cx_ppn,
-                    base_size: parent_inner.base_size,
-                    task_cx: TaskContext::goto_trap_return(kstack_top),
-                    task_status: TaskStatus::Ready,
-                    memory_set,
-                    parent: Some(Arc::downgrade(self)),
-                    children: Vec::new(),
-                    exit_code: 0
-                })
-            }
-        });
-        parent_inner.children.push(ret.clone());
-        let trap_cx = ret.inner_exclusive_access().get_trap_cx();
-        trap_cx.kernel_sp = kstack_top;
-        ret
+    pub fn process(&self) -> Arc<ProcessControlBlock> {
+        self.process.upgrade().unwrap()
     }
-    pub fn exec(&self, elf_data: &[u8]) {
-        let (memory_set,  user_sp, entry_point) = MemorySet::from_elf(elf_data);
-        let trap_cx_ppn = memory_set.translate(VirtAddr::from(TRAP_CONTEXT).into()).unwrap().ppn();
-        let mut inner = self.inner_exclusive_access();
-        inner.memory_set = memory_set;
-        inner.trap_cx_ppn = trap_cx_ppn;
-        let trap_cx = inner.get_trap_cx();
-        *trap_cx = TrapContext::app_init_context(
-            entry_point,
-            user_sp,
-            KERNEL_SPACE.exclusive_access().token(),
-            self.kernal_stack.get_top(),
-            trap_handler as usize
-        )
+    pub fn get_user_token(&self) -> usize {
+        self.process().inner_exclusive_access().get_user_token()
+    }
+    pub fn is_exited(&self) -> bool {
+        self.inner_exclusive_access().task_status == TaskStatus::Exited
     }
-}
\ No newline at end of file
+    /// Whether the thread's affinity allows it to run on `hart`.
+    pub fn may_run_on(&self, hart: usize) -> bool {
+        self.inner_exclusive_access().sched.affinity & (1 << hart) != 0
+    }
+}
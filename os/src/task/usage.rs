@@ -0,0 +1,149 @@
+//! CPU accounting. Every thread stamps the time of its last accounting event, and the time
+//! since is charged to its process as user time when the thread traps into the kernel, and
+//! as kernel time when it returns to user mode or is switched out.
+
+use crate::task::processor::current_task;
+use crate::task::task::TaskControlBlock;
+use crate::timer::get_time_us;
+
+const USEC_PER_SEC: usize = 1_000_000;
+/// Clock ticks per second reported by `times`, like Linux's `USER_HZ`.
+pub const CLOCKS_PER_SEC: usize = 100;
+
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+
+/// What a process has used so far.
+#[derive(Copy, Clone, Default)]
+pub struct CpuUsage {
+    /// Microseconds in user and in kernel mode.
+    pub user_time: usize,
+    pub kernel_time: usize,
+    /// Times a thread blocked, and times one was preempted or yielded.
+    pub voluntary_switches: usize,
+    pub involuntary_switches: usize,
+    pub page_faults: usize
+}
+
+impl CpuUsage {
+    pub fn add(&mut self, other: &Self) {
+        self.user_time += other.user_time;
+        self.kernel_time += other.kernel_time;
+        self.voluntary_switches += other.voluntary_switches;
+        self.involuntary_switches += other.involuntary_switches;
+        self.page_faults += other.page_faults;
+    }
+    pub fn to_rusage(self) -> Rusage {
+        Rusage {
+            ru_utime: TimeVal::from_us(self.user_time),
+            ru_stime: TimeVal::from_us(self.kernel_time),
+            ru_minflt: self.page_faults,
+            ru_nvcsw: self.voluntary_switches,
+            ru_nivcsw: self.involuntary_switches,
+            ..Default::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize
+}
+
+impl TimeVal {
+    fn from_us(us: usize) -> Self {
+        Self { sec: us / USEC_PER_SEC, usec: us % USEC_PER_SEC }
+    }
+}
+
+/// Layout shared with user space for `getrusage`, the same as Linux's. Fields the kernel
+/// does not track stay 0.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    pub ru_maxrss: usize,
+    pub ru_ixrss: usize,
+    pub ru_idrss: usize,
+    pub ru_isrss: usize,
+    pub ru_minflt: usize,
+    pub ru_majflt: usize,
+    pub ru_nswap: usize,
+    pub ru_inblock: usize,
+    pub ru_oublock: usize,
+    pub ru_msgsnd: usize,
+    pub ru_msgrcv: usize,
+    pub ru_nsignals: usize,
+    pub ru_nvcsw: usize,
+    pub ru_nivcsw: usize
+}
+
+/// Layout shared with user space for `times`, in `CLOCKS_PER_SEC` ticks.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Tms {
+    pub tms_utime: usize,
+    pub tms_stime: usize,
+    pub tms_cutime: usize,
+    pub tms_cstime: usize
+}
+
+pub fn us_to_clocks(us: usize) -> usize {
+    us / (USEC_PER_SEC / CLOCKS_PER_SEC)
+}
+
+/// Restarts the accounting of `task`, which is about to run.
+pub fn stamp(task: &TaskControlBlock) {
+    task.inner_exclusive_access().cpu_stamp = get_time_us();
+}
+
+/// Charges the time since the current thread's last stamp to its process.
+fn charge(user: bool) {
+    let Some(task) = current_task() else {
+        return;
+    };
+    let now = get_time_us();
+    let mut task_inner = task.inner_exclusive_access();
+    let elapsed = now - task_inner.cpu_stamp;
+    task_inner.cpu_stamp = now;
+    drop(task_inner);
+    // The process may be gone already when one of its threads is switched out for the last
+    // time.
+    let Some(process) = task.process.upgrade() else {
+        return;
+    };
+    let usage = &mut process.inner_exclusive_access().usage;
+    if user {
+        usage.user_time += elapsed;
+    } else {
+        usage.kernel_time += elapsed;
+    }
+}
+
+/// Called when the current thread traps into the kernel.
+pub fn charge_user_time() {
+    charge(true);
+}
+
+/// Called when the current thread returns to user mode or is switched out.
+pub fn charge_kernel_time() {
+    charge(false);
+}
+
+/// Records that the current thread gives up the CPU, `voluntary` if it blocks.
+pub fn count_switch(voluntary: bool) {
+    let process = current_task().unwrap().process();
+    let usage = &mut process.inner_exclusive_access().usage;
+    if voluntary {
+        usage.voluntary_switches += 1;
+    } else {
+        usage.involuntary_switches += 1;
+    }
+}
+
+pub fn count_page_fault() {
+    current_task().unwrap().process().inner_exclusive_access().usage.page_faults += 1;
+}
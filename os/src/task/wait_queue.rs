@@ -0,0 +1,35 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use crate::task::task::TaskControlBlock;
+use crate::task::wakeup_task;
+
+/// Tasks parked until some event happens. A task pushes itself and then calls
+/// `block_current_and_run_next`, the side that signals the event wakes it up again.
+pub struct WaitQueue {
+    queue: VecDeque<Arc<TaskControlBlock>>
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new()
+        }
+    }
+    pub fn push(&mut self, task: Arc<TaskControlBlock>) {
+        self.queue.push_back(task);
+    }
+    /// Wakes the task that has waited longest, skipping tasks that exited while they waited.
+    /// Returns false if there is nobody left to wake.
+    pub fn wake_one(&mut self) -> bool {
+        while let Some(task) = self.queue.pop_front() {
+            if !task.is_exited() {
+                wakeup_task(task);
+                return true;
+            }
+        }
+        false
+    }
+    pub fn wake_all(&mut self) {
+        while self.wake_one() {}
+    }
+}
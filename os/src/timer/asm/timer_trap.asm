@@ -7,12 +7,23 @@ __timer_traps:
     sd t1, 1 * 8(sp)
     sd t2, 2 * 8(sp)
 
+    # Software interrupts are IPIs from other harts, everything else is the timer.
+    csrr t0, mcause
+    slli t0, t0, 1
+    li t1, 3 << 1
+    bne t0, t1, 1f
+    ld t0, 5 * 8(sp)
+    sw zero, 0(t0)
+    j 2f
+
+1:
     ld t0, 3 * 8(sp)
     ld t1, 4 * 8(sp)
     ld t2, 0(t0)
     add t2, t1, t2
     sd t2, 0(t0)
 
+2:
     li t0, 2
     csrw sip, t0
 
@@ -21,4 +32,4 @@ __timer_traps:
     ld t2, 2 * 8(sp)
     csrrw sp, mscratch, sp
 
-    mret
\ No newline at end of file
+    mret
@@ -1,10 +1,16 @@
 use core::arch::global_asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use riscv::register::{mie, mscratch, mstatus, mtvec};
 use riscv::register::mtvec::{Mtvec, TrapMode};
 use crate::config::*;
-use crate::drivers::misc::{get_time, hart_id, set_mtime_cmp};
+use crate::drivers::misc::{get_mtime_cmp, get_time, hart_id, set_mtime_cmp};
+use crate::task::processor::current_hart;
+
+pub mod sleep_queue;
 
 const MICRO_PER_SEC: usize = 1_000;
+const USEC_PER_SEC: usize = 1_000_000;
+const NSEC_PER_USEC: usize = 1_000;
 
 global_asm!(include_str!("asm/timer_trap.asm"));
 
@@ -12,9 +18,42 @@ pub fn get_time_ms() -> usize {
     unsafe { get_time() / (TIMER_FREQ / MICRO_PER_SEC) }
 }
 
+pub fn get_time_us() -> usize {
+    unsafe { get_time() * USEC_PER_SEC / TIMER_FREQ }
+}
+
+/// Layout shared with user space for `nanosleep` and `clock_gettime`.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize
+}
+
+impl TimeSpec {
+    pub fn from_us(us: usize) -> Self {
+        Self {
+            sec: us / USEC_PER_SEC,
+            nsec: us % USEC_PER_SEC * NSEC_PER_USEC
+        }
+    }
+    /// Rounds up to whole microseconds. Returns None if `nsec` is out of range.
+    pub fn to_us(self) -> Option<usize> {
+        if self.nsec >= USEC_PER_SEC * NSEC_PER_USEC {
+            return None;
+        }
+        self.sec.checked_mul(USEC_PER_SEC)?.checked_add(self.nsec.div_ceil(NSEC_PER_USEC))
+    }
+}
+
+/// Scratch space of the M-mode interrupt handler of each hart. It lives next to the boot
+/// stacks because the harts set it up before the boot hart clears `.bss`.
 #[unsafe(link_section = ".bss.stack")]
 #[unsafe(no_mangle)]
-pub static mut SCRATCH: [[usize; 5]; CPUS] = [[0; 5]; CPUS];
+pub static mut SCRATCH: [[usize; 6]; CPUS] = [[0; 6]; CPUS];
+
+/// `mtimecmp` of each hart when it last checked for a tick.
+static LAST_TICK: [AtomicUsize; CPUS] = [const { AtomicUsize::new(0) }; CPUS];
 
 pub unsafe fn init_timer() {
     let hart_id = hart_id();
@@ -22,9 +61,10 @@ pub unsafe fn init_timer() {
         set_mtime_cmp(TIMER_CMP_BASE_ADDR + (hart_id << 3), get_time() + SCHED_PERIOD);
     }
 
-    let scratch = unsafe { &mut SCRATCH[0] };
+    let scratch = unsafe { &mut SCRATCH[hart_id] };
     scratch[3] = TIMER_CMP_BASE_ADDR + (hart_id << 3);
     scratch[4] = SCHED_PERIOD;
+    scratch[5] = MSIP_BASE_ADDR + (hart_id << 2);
     unsafe {
         mscratch::write(scratch as *const usize as usize);
     }
@@ -34,10 +74,19 @@ pub unsafe fn init_timer() {
     }
     let mut vec = Mtvec::from_bits(0);
     vec.set_trap_mode(TrapMode::Direct);
-    vec.set_address(__timer_traps as usize);
+    vec.set_address(__timer_traps as *const () as usize);
     unsafe {
         mtvec::write(vec);
         mstatus::set_mie();
         mie::set_mtimer();
+        mie::set_msoft();
     }
+}
+
+/// Whether this hart's timer fired since it last asked. Ticks and IPIs both reach S-mode as
+/// the same software interrupt, but only a tick moves `mtimecmp`.
+pub fn timer_ticked() -> bool {
+    let hart = current_hart();
+    let cmp = unsafe { get_mtime_cmp(hart) };
+    LAST_TICK[hart].swap(cmp, Ordering::Relaxed) != cmp
 }
\ No newline at end of file
@@ -0,0 +1,62 @@
+use alloc::collections::BinaryHeap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use lazy_static::lazy_static;
+use crate::sync::spin::SpinLock;
+use crate::task::task::TaskControlBlock;
+use crate::task::wakeup_task;
+use crate::timer::get_time_us;
+
+/// A task sleeping until `deadline`, in microseconds since boot.
+struct SleepingTask {
+    deadline: usize,
+    task: Arc<TaskControlBlock>
+}
+
+impl PartialEq for SleepingTask {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for SleepingTask {}
+
+impl PartialOrd for SleepingTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SleepingTask {
+    /// Reversed so that the `BinaryHeap` pops the earliest deadline first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+lazy_static! {
+    static ref SLEEP_QUEUE: SpinLock<BinaryHeap<SleepingTask>> = SpinLock::new(BinaryHeap::new());
+}
+
+/// Queues `task` to be woken once the time passes `deadline`. The caller blocks it afterwards.
+pub fn add_sleeping_task(deadline: usize, task: Arc<TaskControlBlock>) {
+    SLEEP_QUEUE.exclusive_access().push(SleepingTask { deadline, task });
+}
+
+/// Drops the pending wakeup of `task`, which something else woke before its deadline.
+/// Returns false if there was none, because the deadline has passed already.
+pub fn cancel_sleep(task: &Arc<TaskControlBlock>) -> bool {
+    let mut queue = SLEEP_QUEUE.exclusive_access();
+    let len = queue.len();
+    queue.retain(|sleeping| !Arc::ptr_eq(&sleeping.task, task));
+    queue.len() < len
+}
+
+/// Wakes every task whose deadline has passed.
+pub fn wake_expired_tasks() {
+    let now = get_time_us();
+    let mut queue = SLEEP_QUEUE.exclusive_access();
+    while queue.peek().is_some_and(|sleeping| sleeping.deadline <= now) {
+        wakeup_task(queue.pop().unwrap().task);
+    }
+}
@@ -13,11 +13,10 @@
     .align 2
 __alltraps:
     csrrw sp, sscratch, sp
-    # Save all registers except x0 and x4
+    # Save all registers except x0
     sd x1, 1 * 8(sp)
-    sd x3, 3 * 8(sp)
-    .set n, 5
-    .rept 27
+    .set n, 3
+    .rept 29
         SAVE_GP %n
         .set n, n + 1
     .endr
@@ -28,7 +27,8 @@ __alltraps:
     sd t1, 33 * 8(sp)
     csrr t2, sscratch
     sd t2, 2 * 8(sp)
-    # Set satp, trap handler and kernel sp
+    # Set satp, trap handler, hart id and kernel sp
+    ld tp, 37 * 8(sp)
     ld t0, 34 * 8(sp)
     ld t1, 36 * 8(sp)
     ld sp, 35 * 8(sp)
@@ -50,9 +50,8 @@ __restore:
     csrw sepc, t1
     # Restore all registers
     ld x1, 1 * 8(sp)
-    ld x3, 3 * 8(sp)
-    .set n, 5
-    .rept 27
+    .set n, 3
+    .rept 29
         LOAD_GP %n
         .set n, n + 1
     .endr
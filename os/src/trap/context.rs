@@ -2,13 +2,16 @@ use riscv::register::sstatus;
 use riscv::register::sstatus::{Sstatus, SPP};
 
 #[repr(C)]
+#[derive(Clone)]
 pub struct TrapContext {
     pub reg: [usize; 32],
     pub sstatus: Sstatus,
     pub sepc: usize,
     pub kernel_satp: usize,
     pub kernel_sp: usize,
-    pub trap_handler: usize
+    pub trap_handler: usize,
+    /// Hart the thread last returned to user mode on, loaded into `tp` by the trap entry.
+    pub kernel_hart: usize
 }
 
 impl TrapContext {
@@ -25,7 +28,8 @@ impl TrapContext {
             sepc: entry,
             kernel_satp,
             kernel_sp,
-            trap_handler
+            trap_handler,
+            kernel_hart: 0
         };
         cx.set_sp(sp);
         cx
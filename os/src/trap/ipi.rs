@@ -0,0 +1,79 @@
+use core::arch::asm;
+use core::hint::spin_loop;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use crate::config::CPUS;
+use crate::drivers::misc::send_soft_interrupt;
+use crate::sync::spin::SpinLock;
+use crate::task::processor::current_hart;
+
+/// Request bits a hart can have pending.
+const IPI_CALL: usize = 1 << 0;
+
+/// Requests sent to each hart that it has not served yet.
+static PENDING: [AtomicUsize; CPUS] = [const { AtomicUsize::new(0) }; CPUS];
+
+/// Serialises cross-calls, which share the slots below.
+static CALL_LOCK: SpinLock<()> = SpinLock::new(());
+/// The `fn(usize)` of the cross-call in progress and its argument.
+static CALL_FUNC: AtomicPtr<()> = AtomicPtr::new(null_mut());
+static CALL_ARG: AtomicUsize = AtomicUsize::new(0);
+/// Harts that have not run the cross-call in progress yet, as a bit mask.
+static CALL_WAITING: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `func(arg)` on every hart in the bit mask `harts` and returns once all of them have.
+/// Other harts run it wherever they notice the IPI, possibly while spinning for a lock with
+/// interrupts masked, so `func` must not take locks or allocate.
+pub fn cross_call(harts: usize, func: fn(usize), arg: usize) {
+    let hart = current_hart();
+    if harts & (1 << hart) != 0 {
+        func(arg);
+    }
+    let others = harts & !(1 << hart);
+    if others == 0 {
+        return;
+    }
+    let _call = CALL_LOCK.exclusive_access();
+    CALL_FUNC.store(func as *const () as *mut (), Ordering::SeqCst);
+    CALL_ARG.store(arg, Ordering::SeqCst);
+    CALL_WAITING.store(others, Ordering::SeqCst);
+    for target in (0..CPUS).filter(|target| others & (1 << target) != 0) {
+        PENDING[target].fetch_or(IPI_CALL, Ordering::SeqCst);
+        unsafe { send_soft_interrupt(target); }
+    }
+    // The targets may in turn be waiting for this hart, so keep serving its requests.
+    while CALL_WAITING.load(Ordering::SeqCst) != 0 {
+        handle_ipi();
+        spin_loop();
+    }
+}
+
+/// Serves the requests other harts sent to this one. Besides the software interrupt handler,
+/// every loop that waits in the kernel calls it, since interrupts are masked there.
+pub fn handle_ipi() {
+    let hart = current_hart();
+    if PENDING[hart].load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let pending = PENDING[hart].swap(0, Ordering::SeqCst);
+    if pending & IPI_CALL != 0 {
+        let func: fn(usize) = unsafe { core::mem::transmute(CALL_FUNC.load(Ordering::SeqCst)) };
+        func(CALL_ARG.load(Ordering::SeqCst));
+        CALL_WAITING.fetch_and(!(1 << hart), Ordering::SeqCst);
+    }
+}
+
+/// Makes an idle `hart` leave `wfi`, to look at the ready queues again.
+pub fn wake_hart(hart: usize) {
+    unsafe { send_soft_interrupt(hart); }
+}
+
+fn sfence_vma(_: usize) {
+    unsafe { asm!("sfence.vma"); }
+}
+
+/// Flushes the TLB of this hart and of every hart in the bit mask `harts`, after page table
+/// entries they may have cached were removed or lost permissions.
+pub fn remote_sfence_vma(harts: usize) {
+    cross_call(harts | (1 << current_hart()), sfence_vma, 0);
+}
@@ -3,16 +3,25 @@ use riscv::interrupt::{Exception, Trap, Interrupt};
 use riscv::register::mtvec::TrapMode;
 use riscv::register::{scause, sip, stval, stvec};
 use riscv::register::stvec::Stvec;
-use crate::config::{TRAMPOLINE, TRAP_CONTEXT};
+use crate::config::TRAMPOLINE;
 use crate::drivers::misc::{system_reset, SystemResetOp};
-use crate::drivers::uart::handle_irq;
+use crate::drivers::plic::handle_external_irq;
 use crate::{println, red_msg};
 use crate::syscall::syscall;
-use crate::task::{exit_current_and_run_next, suspend_current_and_run_next};
-use crate::task::processor::{current_trap_cx, current_user_token};
+use crate::task::{exit_process_and_run_next, run_next_if_exited, suspend_current_and_run_next};
+use crate::mem::address::VirtAddr;
+use crate::mem::memory_set::MapPermission;
+use crate::task::manager::tick_task;
+use crate::task::processor::{current_hart, current_process, current_task, current_trap_cx, current_trap_cx_user_va};
+use crate::task::signal::{catch_fault, handle_signals, killed_by, SIGILL, SIGSEGV};
+use crate::task::usage::{charge_kernel_time, charge_user_time, count_page_fault};
+use crate::timer::sleep_queue::wake_expired_tasks;
+use crate::timer::timer_ticked;
 use crate::trap::context::TrapContext;
+use crate::trap::ipi::handle_ipi;
 
 pub mod context;
+pub mod ipi;
 
 global_asm!(include_str!("asm/trap.asm"));
 
@@ -41,9 +50,18 @@ unsafe fn set_user_trap_entry() {
 
 #[unsafe(no_mangle)]
 pub unsafe fn trap_return() -> ! {
+    handle_signals();
+    run_next_if_exited();
+    charge_kernel_time();
+    current_trap_cx().kernel_hart = current_hart();
     unsafe { set_user_trap_entry(); }
-    let trap_cx_ptr = TRAP_CONTEXT;
-    let user_satp = current_user_token();
+    let trap_cx_ptr = current_trap_cx_user_va();
+    let user_satp = {
+        let process = current_process();
+        let inner = process.inner_exclusive_access();
+        inner.memory_set.enter();
+        inner.memory_set.token()
+    };
     unsafe extern "C" {
         fn __alltraps();
         fn __restore();
@@ -73,46 +91,103 @@ pub unsafe fn init_trap() {
     }
 }
 
+/// Tries to resolve a page fault raised by the current application.
+/// Returns false if the access is illegal and the application should be killed.
+fn handle_page_fault(exception: Exception, stval: usize) -> bool {
+    let vpn = VirtAddr::from(stval).floor();
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let access = match exception {
+        Exception::LoadPageFault => MapPermission::R,
+        Exception::StorePageFault => MapPermission::W,
+        Exception::InstructionPageFault => MapPermission::X,
+        _ => return false
+    };
+    let handled = inner.memory_set.handle_page_fault(vpn, access);
+    drop(inner);
+    if handled {
+        count_page_fault();
+    }
+    handled
+}
+
+/// Whether a fault at `stval` hit the guard gap below the current application's stack.
+fn is_stack_overflow(stval: usize) -> bool {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    inner.memory_set.is_stack_guard(VirtAddr::from(stval).floor())
+}
+
+/// Handles whatever interrupts are pending without trapping, for the idle loop which runs
+/// with interrupts masked.
+pub fn poll_interrupts() {
+    let sip = sip::read();
+    if sip.ssoft() {
+        unsafe { sip::clear_ssoft(); }
+        handle_ipi();
+        wake_expired_tasks();
+    }
+    if sip.sext() {
+        handle_external_irq(current_hart());
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe fn trap_handler(cx: &mut TrapContext) -> ! {
     set_kernel_trap_entry();
+    charge_user_time();
+    current_process().inner_exclusive_access().memory_set.leave();
+    run_next_if_exited();
     let mut cx = current_trap_cx();
     let scause = scause::read();
     let stval = stval::read();
     match scause.cause().try_into::<Interrupt, Exception>().unwrap() {
         Trap::Interrupt(Interrupt::SupervisorSoft) => {
             unsafe { sip::clear_ssoft(); }
-            suspend_current_and_run_next();
+            handle_ipi();
+            if timer_ticked() {
+                wake_expired_tasks();
+                if tick_task(&current_task().unwrap()) {
+                    suspend_current_and_run_next();
+                }
+            }
         }
         Trap::Interrupt(Interrupt::SupervisorExternal) => {
-            let sip = sip::read().bits();
-            unsafe {
-                asm! {"csrw sip, {sip}", sip = in(reg) sip ^ (1 << 9)};
-            }
-            handle_irq();
+            handle_external_irq(current_hart());
         }
         Trap::Exception(Exception::UserEnvCall) => {
             cx.sepc += 4;
-            let res = syscall(cx.reg[17], [cx.reg[10], cx.reg[11], cx.reg[12]]) as usize;
+            let res = syscall(cx.reg[17], [cx.reg[10], cx.reg[11], cx.reg[12], cx.reg[13], cx.reg[14], cx.reg[15]]) as usize;
             cx = current_trap_cx();
             cx.reg[10] = res;
         }
-        Trap::Exception(Exception::StoreFault) | 
-        Trap::Exception(Exception::StorePageFault) |
-        Trap::Exception(Exception::InstructionFault) |
-        Trap::Exception(Exception::InstructionPageFault) |
-        Trap::Exception(Exception::LoadFault) |
-        Trap::Exception(Exception::LoadPageFault) => {
-            red_msg!("[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.",
-                scause.cause(),
-                stval,
-                current_trap_cx().sepc,
-            );
-            exit_current_and_run_next(-2);
+        Trap::Exception(exception @ (Exception::StoreFault |
+                                     Exception::StorePageFault |
+                                     Exception::InstructionFault |
+                                     Exception::InstructionPageFault |
+                                     Exception::LoadFault |
+                                     Exception::LoadPageFault)) => {
+            if !handle_page_fault(exception, stval) && !catch_fault(SIGSEGV) {
+                if is_stack_overflow(stval) {
+                    red_msg!("[kernel] Stack overflow in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.",
+                        stval,
+                        current_trap_cx().sepc,
+                    );
+                } else {
+                    red_msg!("[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, core dumped.",
+                        scause.cause(),
+                        stval,
+                        current_trap_cx().sepc,
+                    );
+                }
+                exit_process_and_run_next(killed_by(SIGSEGV));
+            }
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            red_msg!("[kernel] Illegal instruction in application. Kernel killed it.");
-            exit_current_and_run_next(-3);
+            if !catch_fault(SIGILL) {
+                red_msg!("[kernel] Illegal instruction in application. Kernel killed it.");
+                exit_process_and_run_next(killed_by(SIGILL));
+            }
         }
         _ => {
             panic!("Unsupported trap {:?}, trap {:#x}!", scause.cause(), stval);
@@ -1,10 +1,9 @@
 use core::alloc::{GlobalAlloc, Layout};
-use core::cell::UnsafeCell;
 use core::cmp::{max, min};
 use core::mem::size_of;
 use core::ptr::NonNull;
 use crate::{green_msg, yellow_msg};
-use crate::utils::spinlock::SpinLock;
+use crate::sync::spin::SpinLock;
 
 pub struct BuddyAllocator<const ORDER: usize> {
     head: [usize; ORDER],
@@ -14,8 +13,7 @@ pub struct BuddyAllocator<const ORDER: usize> {
 }
 
 pub struct LockedAllocator<const ORDER: usize> {
-    allocator: UnsafeCell<BuddyAllocator<ORDER>>,
-    lock: UnsafeCell<SpinLock>
+    allocator: SpinLock<BuddyAllocator<ORDER>>
 }
 
 fn align_up_to(val: usize, alignment: usize) -> usize {
@@ -176,42 +174,23 @@ impl<const ORDER: usize> BuddyAllocator<ORDER> {
 impl<const ORDER: usize> LockedAllocator<ORDER> {
     pub const fn empty() -> Self {
         Self {
-            allocator: UnsafeCell::new(BuddyAllocator::empty()),
-            lock: UnsafeCell::new(SpinLock::new())
+            allocator: SpinLock::new(BuddyAllocator::empty())
         }
     }
     pub unsafe fn report(&self) {
-        unsafe {
-            (*(self.lock.get())).lock();
-            (*(self.allocator.get())).report();
-            (*(self.lock.get())).unlock();
-        }
+        self.allocator.exclusive_access().report();
     }
     pub unsafe fn init(&self, start: usize, size: usize) {
-        unsafe {
-            (*(self.lock.get())).lock();
-            (*(self.allocator.get())).init(start, size);
-            (*(self.lock.get())).unlock();
-        }
+        unsafe { self.allocator.exclusive_access().init(start, size); }
     }
 }
 
 unsafe impl<const ORDER: usize> GlobalAlloc for LockedAllocator<ORDER> {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        unsafe {
-            (*(self.lock.get())).lock();
-            let res = (*(self.allocator.get())).alloc(layout);
-            (*(self.lock.get())).unlock();
-            res.ok().unwrap().as_ptr()
-        }
+        let res = unsafe { self.allocator.exclusive_access().alloc(layout) };
+        res.ok().unwrap().as_ptr()
     }
     unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-        unsafe {
-            (*(self.lock.get())).lock();
-            (*(self.allocator.get())).dealloc(NonNull::<u8>::new(ptr).unwrap(), layout);
-            (*(self.lock.get())).unlock();
-        }
+        unsafe { self.allocator.exclusive_access().dealloc(NonNull::<u8>::new(ptr).unwrap(), layout); }
     }
 }
-
-unsafe impl<const ORDER: usize> Sync for LockedAllocator<ORDER> {}
\ No newline at end of file
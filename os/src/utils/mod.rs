@@ -1,2 +1 @@
-pub mod allocator;
-pub mod spinlock;
\ No newline at end of file
+pub mod allocator;
\ No newline at end of file
@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{cap_copy, cap_delete, cap_identify, cap_mint, cap_move, cap_revoke, endpoint_create, exit, fork, frame_alloc, frame_map, recv, send, waitpid};
+use user_lib::{CAP_ADDRESS_SPACE, CAP_READ, CAP_TYPE_ADDRESS_SPACE, CAP_TYPE_ENDPOINT, CAP_TYPE_FRAME, CAP_WRITE, PROT_READ, PROT_WRITE};
+
+const COPY: usize = 20;
+const MOVED: usize = 21;
+const MAPPED: usize = 0x10_0000_0000;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert_eq!(cap_identify(CAP_ADDRESS_SPACE), CAP_TYPE_ADDRESS_SPACE);
+    let endpoint = endpoint_create();
+    assert_eq!(cap_identify(endpoint as usize), CAP_TYPE_ENDPOINT);
+
+    // Copy, move and delete within our own capability space.
+    assert_eq!(cap_copy(endpoint as usize, COPY), 0);
+    assert!(cap_copy(endpoint as usize, COPY) < 0);
+    assert_eq!(cap_move(COPY, MOVED), 0);
+    assert!(cap_identify(COPY) < 0);
+    assert_eq!(cap_identify(MOVED), CAP_TYPE_ENDPOINT);
+    assert_eq!(cap_delete(MOVED), 0);
+    assert!(cap_delete(MOVED) < 0);
+    println!("copy, move and delete ok.");
+
+    // Minted copies never gain rights.
+    assert_eq!(cap_mint(endpoint as usize, COPY, CAP_READ, 0), 0);
+    assert!(send(COPY, &[0; 4]) < 0);
+    assert_eq!(cap_mint(COPY, MOVED, CAP_READ | CAP_WRITE, 0), 0);
+    assert!(send(MOVED, &[0; 4]) < 0);
+    println!("mint ok.");
+
+    // Revoking removes every derived copy, including those a child inherited, but keeps
+    // the original.
+    assert_eq!(cap_revoke(endpoint as usize), 0);
+    assert!(cap_identify(COPY) < 0);
+    assert!(cap_identify(MOVED) < 0);
+    assert_eq!(cap_copy(endpoint as usize, COPY), 0);
+    let pid = fork();
+    if pid == 0 {
+        // Wait until the parent revokes our copies.
+        let mut msg = [0; 4];
+        recv(endpoint as usize, &mut msg);
+        exit(cap_identify(COPY) as i32);
+    }
+    assert_eq!(cap_revoke(COPY), 0);
+    assert_eq!(cap_identify(COPY), CAP_TYPE_ENDPOINT);
+    assert_eq!(send(endpoint as usize, &[0; 4]), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert!(exit_code < 0);
+    assert_eq!(cap_delete(COPY), 0);
+    println!("revoke ok.");
+
+    // Frames can only be mapped as far as the capability allows.
+    let frame = frame_alloc();
+    assert_eq!(cap_identify(frame as usize), CAP_TYPE_FRAME);
+    assert_eq!(frame_map(CAP_ADDRESS_SPACE, frame as usize, MAPPED, PROT_READ | PROT_WRITE), 0);
+    unsafe { (MAPPED as *mut usize).write_volatile(0x1234); }
+    assert_eq!(cap_mint(frame as usize, COPY, CAP_READ, 0), 0);
+    let ro = MAPPED + 0x1000;
+    assert!(frame_map(CAP_ADDRESS_SPACE, COPY, ro, PROT_READ | PROT_WRITE) < 0);
+    assert_eq!(frame_map(CAP_ADDRESS_SPACE, COPY, ro, PROT_READ), 0);
+    assert_eq!(unsafe { (ro as *const usize).read_volatile() }, 0x1234);
+    assert!(frame_map(frame as usize, frame as usize, ro, PROT_READ) < 0);
+    println!("frame map ok.");
+
+    println!("cap_test passed!");
+    0
+}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{call, cap_mint, cap_move, endpoint_create, exec, exit, fork, recv, reply, send, waitpid, IpcMessage, CAP_WRITE};
+
+/// The slot echo_server expects its endpoint in.
+const ENDPOINT: usize = 16;
+const BADGED: usize = 17;
+const BADGE: usize = 42;
+
+const OP_ECHO: usize = 1;
+const OP_BADGE: usize = 2;
+const OP_QUIT: usize = 3;
+const ROUNDS: usize = 100;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let endpoint = endpoint_create();
+    assert!(endpoint >= 0);
+    assert_eq!(cap_move(endpoint as usize, ENDPOINT), 0);
+    let pid = fork();
+    if pid == 0 {
+        exec("echo_server\0");
+        exit(-1);
+    }
+    // Nobody has called us, so there is nothing to reply to.
+    assert!(reply(&[0; 4]) < 0);
+    for i in 0..ROUNDS {
+        let mut msg: IpcMessage = [OP_ECHO, i, i * i, usize::MAX - i];
+        assert_eq!(call(ENDPOINT, &mut msg), 0);
+        assert_eq!(msg, [OP_ECHO, i, i * i, usize::MAX - i]);
+    }
+    println!("echo_client: {} calls echoed.", ROUNDS);
+
+    // A badged, send-only copy identifies us to the server but cannot receive.
+    assert_eq!(cap_mint(ENDPOINT, BADGED, CAP_WRITE, BADGE), 0);
+    let mut msg: IpcMessage = [OP_BADGE, 0, 0, 0];
+    assert_eq!(call(BADGED, &mut msg), 0);
+    assert_eq!(msg[1], BADGE);
+    assert!(recv(BADGED, &mut msg) < 0);
+    assert!(cap_mint(BADGED, BADGED + 1, CAP_WRITE, BADGE + 1) < 0);
+    println!("echo_client: badge {} seen by the server.", BADGE);
+
+    assert_eq!(send(ENDPOINT, &[OP_QUIT, 0, 0, 0]), 0);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("echo_client passed!");
+    0
+}
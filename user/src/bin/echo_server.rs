@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{recv, reply, IpcMessage};
+
+/// Serves on the endpoint in this slot, which it inherits from the client that started it.
+const ENDPOINT: usize = 16;
+
+const OP_ECHO: usize = 1;
+const OP_BADGE: usize = 2;
+const OP_QUIT: usize = 3;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut msg: IpcMessage = [0; 4];
+    let mut served = 0;
+    loop {
+        let badge = recv(ENDPOINT, &mut msg);
+        if badge < 0 {
+            println!("echo_server: recv failed with {}", badge);
+            return -1;
+        }
+        match msg[0] {
+            OP_ECHO => {
+                served += 1;
+                assert_eq!(reply(&msg), 0);
+            }
+            OP_BADGE => {
+                assert_eq!(reply(&[OP_BADGE, badge as usize, 0, 0]), 0);
+            }
+            OP_QUIT => {
+                println!("echo_server: served {} calls, bye.", served);
+                return 0;
+            }
+            op => {
+                println!("echo_server: unknown op {} through badge {}", op, badge);
+            }
+        }
+    }
+}
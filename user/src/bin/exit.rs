@@ -3,7 +3,7 @@
 
 #[macro_use]
 extern crate user_lib;
-use user_lib::{exit, fork, wait, waitpid, yield_};
+use user_lib::{exit, fork, wait, waitpid, waitpid_options, yield_, WNOHANG};
 
 const MAGIC: i32 = -0x10384;
 
@@ -22,6 +22,7 @@ pub fn main() -> i32 {
     }
     println!("I am the parent, waiting now..");
     let mut xstate: i32 = 0;
+    assert_eq!(waitpid_options(pid, &mut xstate, WNOHANG), -2);
     assert!(waitpid(pid as usize, &mut xstate) == pid && xstate == MAGIC);
     assert!(waitpid(pid as usize, &mut xstate) < 0 && wait(&mut xstate) <= 0);
     println!("waitpid {} ok.", pid);
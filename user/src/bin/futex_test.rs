@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicU32, Ordering};
+use user_lib::{exit, fork, futex_wait, futex_wake, get_sched_stat, get_time_us, sched_setaffinity, shm_create, shm_map, sleep, thread_create, waitpid, waittid, yield_, FutexMutex, SchedStat, TimeSpec, CAP_ADDRESS_SPACE, EAGAIN, ETIMEDOUT, PROT_READ, PROT_WRITE};
+
+const THREADS: usize = 4;
+const INCREMENTS: usize = 2000;
+const MAX_HARTS: usize = 8;
+
+static COUNTER: FutexMutex<usize> = FutexMutex::new(0);
+static CONTENDED: FutexMutex<usize> = FutexMutex::new(0);
+
+fn increment(_: usize) -> ! {
+    for _ in 0..INCREMENTS {
+        let mut counter = COUNTER.lock();
+        let value = *counter;
+        // Give the other threads a chance to run while the lock is held.
+        if value.is_multiple_of(100) {
+            yield_();
+        }
+        *counter = value + 1;
+    }
+    exit(0)
+}
+
+fn threads_share_lock() {
+    let tids: [usize; THREADS] = core::array::from_fn(|_| thread_create(increment, 0) as usize);
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    assert_eq!(*COUNTER.lock(), THREADS * INCREMENTS);
+}
+
+/// Runs on the hart in `arg` and takes the lock without ever yielding, so that waiters and
+/// wakers race on different harts. A lost wakeup leaves a thread blocked for good.
+fn contend(hart: usize) -> ! {
+    assert_eq!(sched_setaffinity(0, 1 << hart), 0);
+    for _ in 0..INCREMENTS {
+        *CONTENDED.lock() += 1;
+    }
+    exit(0)
+}
+
+fn threads_contend_across_harts() {
+    let mut stat = SchedStat::default();
+    let harts = (0..MAX_HARTS).take_while(|&hart| get_sched_stat(hart, &mut stat) == 0).count();
+    let threads = 2 * harts;
+    let mut tids = [0; 2 * MAX_HARTS];
+    for (i, tid) in tids.iter_mut().take(threads).enumerate() {
+        *tid = thread_create(contend, i % harts) as usize;
+    }
+    for &tid in tids.iter().take(threads) {
+        assert_eq!(waittid(tid), 0);
+    }
+    assert_eq!(*CONTENDED.lock(), threads * INCREMENTS);
+}
+
+fn wait_checks_value_and_times_out() {
+    let word = AtomicU32::new(1);
+    assert_eq!(futex_wait(&word, 0, None), EAGAIN);
+    assert_eq!(futex_wake(&word, 1), 0);
+    let timeout = TimeSpec { sec: 0, nsec: 10_000_000 };
+    let start = get_time_us();
+    assert_eq!(futex_wait(&word, 1, Some(&timeout)), ETIMEDOUT);
+    assert!(get_time_us() - start >= 10_000);
+}
+
+fn processes_share_word() {
+    let shm = shm_create(4096);
+    assert!(shm >= 0);
+    let addr = shm_map(CAP_ADDRESS_SPACE, shm as usize, 0, PROT_READ | PROT_WRITE);
+    assert!(addr > 0);
+    let word = unsafe { &*(addr as *const AtomicU32) };
+    let pid = fork();
+    if pid == 0 {
+        while word.load(Ordering::Acquire) == 0 {
+            futex_wait(word, 0, None);
+        }
+        exit(0);
+    }
+    // Give the child time to go to sleep, so the wakeup has to cross address spaces.
+    sleep(10);
+    word.store(1, Ordering::Release);
+    futex_wake(word, 1);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    threads_share_lock();
+    threads_contend_across_harts();
+    wait_checks_value_and_times_out();
+    processes_share_word();
+    println!("futex_test passed!");
+    0
+}
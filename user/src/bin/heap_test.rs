@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{brk, sbrk};
+
+const PAGE_SIZE: usize = 4096;
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // Grow and shrink the break directly.
+    let old_brk = sbrk(0);
+    assert!(old_brk > 0);
+    assert_eq!(sbrk(2 * PAGE_SIZE as isize), old_brk);
+    let ptr = old_brk as *mut usize;
+    unsafe {
+        ptr.write_volatile(0xdead);
+        ptr.add(PAGE_SIZE / size_of::<usize>()).write_volatile(0xbeef);
+        assert_eq!(ptr.read_volatile(), 0xdead);
+    }
+    assert_eq!(brk(old_brk as usize), old_brk);
+    assert_eq!(sbrk(0), old_brk);
+    println!("brk grow and shrink ok.");
+
+    // Allocate far more than the initial heap used to hold.
+    let mut blocks: Vec<Vec<u8>> = Vec::new();
+    for i in 0..64 {
+        blocks.push(alloc::vec![i as u8; 16 * 1024]);
+    }
+    for (i, block) in blocks.iter().enumerate() {
+        assert!(block.iter().all(|&b| b == i as u8));
+    }
+    drop(blocks);
+    let big: Vec<usize> = (0..256 * 1024).collect();
+    assert_eq!(big.iter().sum::<usize>(), 256 * 1024 * (256 * 1024 - 1) / 2);
+    println!("heap_test passed!");
+    0
+}
@@ -4,10 +4,30 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{exec, fork, wait, yield_};
+use user_lib::{cap_delete, cap_mint, cap_move, exec, fork, wait, yield_, CAP_IRQ_CONTROL, CAP_NAME_SERVER, CAP_WRITE};
+
+/// Free slot used while swapping the name server capability for a send-only one.
+const SCRATCH: usize = 4;
 
 #[unsafe(no_mangle)]
 fn main() -> i32 {
+    if fork() == 0 {
+        assert_eq!(cap_delete(CAP_IRQ_CONTROL), 0);
+        exec("name_server\0");
+        return -1;
+    }
+    // Only the name server may receive on its endpoint, everyone else inherits a copy
+    // that can just call it.
+    assert_eq!(cap_mint(CAP_NAME_SERVER, SCRATCH, CAP_WRITE, 0), 0);
+    assert_eq!(cap_delete(CAP_NAME_SERVER), 0);
+    assert_eq!(cap_move(SCRATCH, CAP_NAME_SERVER), 0);
+    // The UART driver is the only process to handle IRQs and map devices. Every later
+    // child would inherit the capability, so it goes right after.
+    if fork() == 0 {
+        exec("uart_driver\0");
+        return -1;
+    }
+    assert_eq!(cap_delete(CAP_IRQ_CONTROL), 0);
     if fork() == 0 {
         exec("user_shell\0");
     } else {
@@ -26,4 +46,4 @@ fn main() -> i32 {
         }
     }
     0
-}
\ No newline at end of file
+}
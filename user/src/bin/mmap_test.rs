@@ -0,0 +1,72 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, mmap, mprotect, munmap, read, waitpid, EFAULT, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+const START: usize = 0x10_0000_0000;
+const PAGES: usize = 4;
+
+fn page(i: usize) -> *mut usize {
+    (START + i * PAGE_SIZE) as *mut usize
+}
+
+/// Runs `f` in a child process and returns its exit code.
+fn in_child(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let prot = PROT_READ | PROT_WRITE;
+    assert!(mmap(START + 1, PAGE_SIZE, prot) < 0);
+    assert!(mmap(START, PAGE_SIZE, PROT_WRITE) < 0);
+    assert_eq!(mmap(START, PAGES * PAGE_SIZE, prot), START as isize);
+    assert!(mmap(START + PAGE_SIZE, PAGE_SIZE, prot) < 0);
+    for i in 0..PAGES {
+        unsafe { page(i).write_volatile(i); }
+    }
+    println!("mmap {} pages ok.", PAGES);
+
+    // Punch a hole in the middle of the area.
+    assert_eq!(munmap(START + PAGE_SIZE, PAGE_SIZE), 0);
+    for i in [0, 2, 3] {
+        assert_eq!(unsafe { page(i).read_volatile() }, i);
+    }
+    assert_eq!(in_child(|| unsafe { page(1).write_volatile(1) }), -2);
+    assert!(mprotect(START, PAGES * PAGE_SIZE, PROT_READ) < 0);
+    println!("munmap in the middle ok.");
+
+    // Map the hole again and make only the page after it read-only.
+    assert_eq!(mmap(START + PAGE_SIZE, PAGE_SIZE, prot), (START + PAGE_SIZE) as isize);
+    assert_eq!(unsafe { page(1).read_volatile() }, 0);
+    assert_eq!(mprotect(START + 2 * PAGE_SIZE, PAGE_SIZE, PROT_READ), 0);
+    assert_eq!(unsafe { page(2).read_volatile() }, 2);
+    assert_eq!(in_child(|| unsafe { page(2).write_volatile(0) }), -2);
+    unsafe { page(3).write_volatile(33); }
+    assert_eq!(unsafe { page(3).read_volatile() }, 33);
+    println!("mprotect in the middle ok.");
+
+    assert_eq!(munmap(START, PAGES * PAGE_SIZE), 0);
+    // The kernel writes no more than the process could write itself.
+    let unmapped = unsafe { core::slice::from_raw_parts_mut(page(0) as *mut u8, 8) };
+    assert_eq!(read(0, unmapped), EFAULT);
+    let text = unsafe { core::slice::from_raw_parts_mut(main as *const () as *mut u8, 8) };
+    assert_eq!(read(0, text), EFAULT);
+    println!("EFAULT for bad buffers ok.");
+    let addr = mmap(0, PAGE_SIZE, prot);
+    assert!(addr > 0);
+    assert_eq!(munmap(addr as usize, PAGE_SIZE), 0);
+    println!("mmap_test passed!");
+    0
+}
@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use user_lib::service::{NS_EXISTS, NS_FULL, NS_INVALID, NS_LOOKUP, NS_NOT_FOUND, NS_REGISTER};
+use user_lib::{cap_delete, cap_identify, cap_mint, cap_move, recv_cap, reply_cap, IpcMessage, CAP_NAME_SERVER, CAP_TYPE_ENDPOINT, CAP_WRITE, CSPACE_SLOTS, NO_CAP};
+
+/// Free slot used while swapping a registered endpoint for a send-only copy. Received
+/// capabilities take the lowest free slot, so this one is the last to fill up.
+const SCRATCH: usize = CSPACE_SLOTS - 1;
+
+/// Replaces the capability in `slot` by a copy that can only send, so that clients looking
+/// the endpoint up cannot receive the server's requests.
+fn restrict_to_send(slot: usize) -> bool {
+    if cap_mint(slot, SCRATCH, CAP_WRITE, 0) != 0 {
+        return false;
+    }
+    cap_delete(slot);
+    cap_move(SCRATCH, slot) == 0
+}
+
+/// Serves the endpoint initproc hands down to every process, keeping the slot of the
+/// endpoint registered under each name.
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut services: BTreeMap<[usize; 3], usize> = BTreeMap::new();
+    let mut msg: IpcMessage = [0; 4];
+    loop {
+        let mut cap = NO_CAP;
+        if recv_cap(CAP_NAME_SERVER, &mut msg, &mut cap) < 0 {
+            println!("name_server: cannot receive on the name server endpoint.");
+            return -1;
+        }
+        let name = [msg[1], msg[2], msg[3]];
+        let (status, endpoint) = match msg[0] {
+            NS_REGISTER if cap == NO_CAP || cap_identify(cap) != CAP_TYPE_ENDPOINT => (NS_INVALID, NO_CAP),
+            NS_REGISTER if services.contains_key(&name) => (NS_EXISTS, NO_CAP),
+            NS_REGISTER if !restrict_to_send(cap) => (NS_FULL, NO_CAP),
+            NS_REGISTER => {
+                services.insert(name, cap);
+                cap = NO_CAP;
+                (0, NO_CAP)
+            }
+            NS_LOOKUP => match services.get(&name) {
+                Some(&endpoint) => (0, endpoint),
+                None => (NS_NOT_FOUND, NO_CAP)
+            },
+            _ => (NS_INVALID, NO_CAP)
+        };
+        if cap != NO_CAP {
+            cap_delete(cap);
+        }
+        reply_cap(&[status as usize, 0, 0, 0], endpoint);
+    }
+}
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::service::{NS_EXISTS, NS_INVALID, NS_NOT_FOUND};
+use user_lib::{cap_identify, endpoint_create, exit, fork, lookup_service, recv, register_service, send, waitpid, IpcMessage, CAP_ADDRESS_SPACE, CAP_NAME_SERVER, CAP_TYPE_ENDPOINT};
+
+const NAME: &str = "name_service_test";
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // Everyone can call the name server, but only the name server receives on it.
+    assert_eq!(cap_identify(CAP_NAME_SERVER), CAP_TYPE_ENDPOINT);
+    let mut msg: IpcMessage = [0; 4];
+    assert!(recv(CAP_NAME_SERVER, &mut msg) < 0);
+
+    let endpoint = endpoint_create();
+    assert!(endpoint >= 0);
+    let endpoint = endpoint as usize;
+    assert_eq!(lookup_service(NAME), NS_NOT_FOUND);
+    assert_eq!(register_service(NAME, endpoint), 0);
+    assert_eq!(register_service(NAME, endpoint), NS_EXISTS);
+    assert_eq!(register_service("", endpoint), NS_INVALID);
+    assert_eq!(register_service("a name that is far too long", endpoint), NS_INVALID);
+    assert_eq!(register_service("not an endpoint", CAP_ADDRESS_SPACE), NS_INVALID);
+    println!("register ok.");
+
+    // A process that only knows the name reaches the server through the looked up copy.
+    let pid = fork();
+    if pid == 0 {
+        let found = lookup_service(NAME);
+        assert!(found >= 0);
+        assert_eq!(cap_identify(found as usize), CAP_TYPE_ENDPOINT);
+        // The copy handed out can only send, the server's requests stay its own.
+        assert!(recv(found as usize, &mut msg) < 0);
+        assert_eq!(send(found as usize, &[1, 2, 3, 4]), 0);
+        exit(0);
+    }
+    assert_eq!(recv(endpoint, &mut msg), 0);
+    assert_eq!(msg, [1, 2, 3, 4]);
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("name_service_test passed!");
+    0
+}
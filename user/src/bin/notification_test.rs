@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{cap_identify, cap_mint, endpoint_create, exit, fork, notification_bind, notification_create, poll, recv, send, signal, wait_notification, waitpid, yield_, IpcMessage, CAP_READ, CAP_TYPE_NOTIFICATION, CAP_WRITE, NOTIFICATION_BADGE};
+
+const BADGED: usize = 20;
+const READ_ONLY: usize = 21;
+
+fn join(pid: isize) {
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let ntfn = notification_create();
+    assert!(ntfn >= 0);
+    let ntfn = ntfn as usize;
+    assert_eq!(cap_identify(ntfn), CAP_TYPE_NOTIFICATION);
+    assert_eq!(poll(ntfn), 0);
+    assert!(signal(ntfn, 0) < 0);
+    assert_eq!(signal(ntfn, 0b001), 0);
+    assert_eq!(signal(ntfn, 0b100), 0);
+    assert_eq!(poll(ntfn), 0b101);
+    assert_eq!(poll(ntfn), 0);
+
+    // A badged capability ORs its badge in; a read-only one cannot signal.
+    assert_eq!(cap_mint(ntfn, BADGED, CAP_WRITE, 0b1000), 0);
+    assert_eq!(signal(BADGED, 0b10), 0);
+    assert_eq!(wait_notification(ntfn), 0b1010);
+    assert!(wait_notification(BADGED) < 0);
+    assert_eq!(cap_mint(ntfn, READ_ONLY, CAP_READ, 0), 0);
+    assert!(signal(READ_ONLY, 1) < 0);
+    println!("signal and poll ok.");
+
+    // Waiting blocks until another process signals.
+    let pid = fork();
+    if pid == 0 {
+        for _ in 0..10 {
+            yield_();
+        }
+        assert_eq!(signal(ntfn, 0b110), 0);
+        exit(0);
+    }
+    assert_eq!(wait_notification(ntfn), 0b110);
+    join(pid);
+    println!("blocking wait ok.");
+
+    // A server blocked in recv is woken by either a message or the bound notification.
+    let endpoint = endpoint_create();
+    assert!(endpoint >= 0);
+    let endpoint = endpoint as usize;
+    assert_eq!(notification_bind(endpoint, ntfn), 0);
+    assert!(notification_bind(endpoint, ntfn) < 0);
+    let mut msg: IpcMessage = [0; 4];
+    assert_eq!(signal(ntfn, 0b11), 0);
+    assert_eq!(recv(endpoint, &mut msg), NOTIFICATION_BADGE);
+    assert_eq!(msg[0], 0b11);
+    let pid = fork();
+    if pid == 0 {
+        for _ in 0..10 {
+            yield_();
+        }
+        assert_eq!(signal(ntfn, 0b100), 0);
+        assert_eq!(send(endpoint, &[1, 2, 3, 4]), 0);
+        exit(0);
+    }
+    assert_eq!(recv(endpoint, &mut msg), NOTIFICATION_BADGE);
+    assert_eq!(msg[0], 0b100);
+    assert_eq!(recv(endpoint, &mut msg), 0);
+    assert_eq!(msg, [1, 2, 3, 4]);
+    join(pid);
+    println!("notification_test passed!");
+    0
+}
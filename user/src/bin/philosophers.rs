@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, mutex_blocking_create, mutex_create, mutex_lock, mutex_unlock, sleep, thread_create, waittid, yield_, Mutex};
+
+const N: usize = 5;
+const ROUNDS: usize = 20;
+
+struct Table {
+    forks: [usize; N],
+    /// Who is eating right now and how often everyone has eaten.
+    state: Mutex<([bool; N], [usize; N])>
+}
+
+fn philosopher(arg: usize) -> ! {
+    let (table, id) = unsafe { *(arg as *const (&Table, usize)) };
+    let (left, right) = (id, (id + 1) % N);
+    // Always taking the lower-numbered fork first rules out a cycle of waiting philosophers.
+    let (first, second) = (left.min(right), left.max(right));
+    for _ in 0..ROUNDS {
+        mutex_lock(table.forks[first]);
+        mutex_lock(table.forks[second]);
+        {
+            let mut state = table.state.lock();
+            assert!(!state.0[(id + N - 1) % N] && !state.0[(id + 1) % N]);
+            state.0[id] = true;
+        }
+        sleep(1);
+        {
+            let mut state = table.state.lock();
+            state.0[id] = false;
+            state.1[id] += 1;
+        }
+        mutex_unlock(table.forks[second]);
+        mutex_unlock(table.forks[first]);
+        yield_();
+    }
+    exit(0)
+}
+
+fn dine(new_fork: fn() -> isize) {
+    let mut forks = [0; N];
+    for fork in forks.iter_mut() {
+        *fork = new_fork() as usize;
+    }
+    let table = Table { forks, state: Mutex::new(([false; N], [0; N])) };
+    let seats: [(&Table, usize); N] = core::array::from_fn(|id| (&table, id));
+    let mut tids = [0; N];
+    for (seat, tid) in seats.iter().zip(tids.iter_mut()) {
+        *tid = thread_create(philosopher, seat as *const _ as usize) as usize;
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    assert_eq!(table.state.lock().1, [ROUNDS; N]);
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    dine(mutex_create);
+    dine(mutex_blocking_create);
+    println!("philosophers passed!");
+    0
+}
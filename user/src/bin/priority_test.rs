@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{exit, get_idle_time, nice, sched_setaffinity, set_priority, sleep, thread_create, waittid, DEFAULT_PRIORITY, ENOSYS, EPERM};
+
+const PRIORITIES: [isize; 3] = [DEFAULT_PRIORITY, 2 * DEFAULT_PRIORITY, 4 * DEFAULT_PRIORITY];
+const RUN_MS: usize = 1000;
+/// Threads started for each priority, enough to keep up to this many harts busy.
+const MAX_HARTS: usize = 8;
+const MAX_THREADS: usize = PRIORITIES.len() * MAX_HARTS;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+static COUNTS: [AtomicUsize; MAX_THREADS] = [const { AtomicUsize::new(0) }; MAX_THREADS];
+
+fn busy_loop(index: usize) -> ! {
+    let prio = PRIORITIES[index % PRIORITIES.len()];
+    assert_eq!(set_priority(prio), prio);
+    assert_eq!(sched_setaffinity(0, 1 << (index / PRIORITIES.len())), 0);
+    while !STOP.load(Ordering::Relaxed) {
+        COUNTS[index].fetch_add(1, Ordering::Relaxed);
+    }
+    exit(0)
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    // initproc, pid 1, is not a descendant of this process.
+    assert_eq!(sched_setaffinity(1, 1), EPERM);
+    if set_priority(DEFAULT_PRIORITY) == ENOSYS {
+        println!("priority_test skipped, the scheduler ignores priorities");
+        return 0;
+    }
+    assert_eq!(nice(5), 5);
+    assert_eq!(nice(-100), -20);
+    assert_eq!(nice(20), 0);
+    assert!(set_priority(0) < 0);
+
+    // Every hart gets one thread of each priority to share out. Harts schedule their own
+    // ready queues, so the threads are pinned to keep the mix from drifting.
+    let harts = (0..MAX_HARTS).take_while(|&hart| get_idle_time(hart) >= 0).count();
+    let threads = PRIORITIES.len() * harts;
+    let mut tids = [0; MAX_THREADS];
+    for (index, tid) in tids.iter_mut().enumerate().take(threads) {
+        *tid = thread_create(busy_loop, index) as usize;
+    }
+    sleep(RUN_MS);
+    STOP.store(true, Ordering::Relaxed);
+    for &tid in tids.iter().take(threads) {
+        assert_eq!(waittid(tid), 0);
+    }
+    // Iterations per unit of priority should be about the same for every priority.
+    let shares: [usize; PRIORITIES.len()] = core::array::from_fn(|index| {
+        let count: usize = (index..threads).step_by(PRIORITIES.len())
+            .map(|thread| COUNTS[thread].load(Ordering::Relaxed))
+            .sum();
+        count / PRIORITIES[index] as usize
+    });
+    let (min, max) = (*shares.iter().min().unwrap(), *shares.iter().max().unwrap());
+    println!("priority_test: iterations per priority {:?}", shares);
+    assert!(min > 0 && max * 2 < min * 3);
+    println!("priority_test passed!");
+    0
+}
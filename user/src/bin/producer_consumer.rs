@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use user_lib::{condvar_create, condvar_signal, condvar_wait, exit, semaphore_create, semaphore_down, semaphore_up, thread_create, waittid, Mutex};
+
+const PRODUCERS: usize = 4;
+const ITEMS: usize = 100;
+const CAPACITY: usize = 8;
+
+/// A bounded buffer guarded by semaphores counting free and filled slots.
+struct SemaphoreBuffer {
+    empty: usize,
+    full: usize,
+    items: Mutex<VecDeque<usize>>
+}
+
+/// An unbounded buffer whose consumer sleeps on a condition variable while it is empty.
+struct CondvarBuffer {
+    nonempty: usize,
+    items: Mutex<VecDeque<usize>>
+}
+
+fn produce_bounded(arg: usize) -> ! {
+    let buffer = unsafe { &*(arg as *const SemaphoreBuffer) };
+    for item in 1..=ITEMS {
+        semaphore_down(buffer.empty);
+        let mut items = buffer.items.lock();
+        assert!(items.len() < CAPACITY);
+        items.push_back(item);
+        drop(items);
+        semaphore_up(buffer.full);
+    }
+    exit(0)
+}
+
+fn produce_unbounded(arg: usize) -> ! {
+    let buffer = unsafe { &*(arg as *const CondvarBuffer) };
+    for item in 1..=ITEMS {
+        buffer.items.lock().push_back(item);
+        condvar_signal(buffer.nonempty);
+    }
+    exit(0)
+}
+
+fn expected_sum() -> usize {
+    PRODUCERS * ITEMS * (ITEMS + 1) / 2
+}
+
+fn with_semaphores() {
+    let buffer = SemaphoreBuffer {
+        empty: semaphore_create(CAPACITY) as usize,
+        full: semaphore_create(0) as usize,
+        items: Mutex::new(VecDeque::new())
+    };
+    let tids: [usize; PRODUCERS] = core::array::from_fn(|_| {
+        thread_create(produce_bounded, &buffer as *const _ as usize) as usize
+    });
+    let mut sum = 0;
+    for _ in 0..PRODUCERS * ITEMS {
+        semaphore_down(buffer.full);
+        sum += buffer.items.lock().pop_front().unwrap();
+        semaphore_up(buffer.empty);
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    assert_eq!(sum, expected_sum());
+}
+
+fn with_condvar() {
+    let buffer = CondvarBuffer {
+        nonempty: condvar_create() as usize,
+        items: Mutex::new(VecDeque::new())
+    };
+    let tids: [usize; PRODUCERS] = core::array::from_fn(|_| {
+        thread_create(produce_unbounded, &buffer as *const _ as usize) as usize
+    });
+    let mut sum = 0;
+    for _ in 0..PRODUCERS * ITEMS {
+        let mut items = buffer.items.lock();
+        while items.is_empty() {
+            condvar_wait(buffer.nonempty, buffer.items.id());
+        }
+        sum += items.pop_front().unwrap();
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid), 0);
+    }
+    assert_eq!(sum, expected_sum());
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    with_semaphores();
+    with_condvar();
+    println!("producer_consumer passed!");
+    0
+}
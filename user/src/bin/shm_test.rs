@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{cap_identify, cap_mint, exit, fork, mprotect, munmap, shm_create, shm_map, waitpid, CAP_ADDRESS_SPACE, CAP_READ, CAP_TYPE_SHARED_MEMORY, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 4;
+const READ_ONLY: usize = 20;
+
+fn words(addr: usize) -> &'static mut [usize] {
+    unsafe { core::slice::from_raw_parts_mut(addr as *mut usize, PAGES * PAGE_SIZE / 8) }
+}
+
+/// Runs `f` in a child process and returns its exit code.
+fn in_child(f: impl FnOnce()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert!(shm_create(0) < 0);
+    let shm = shm_create(PAGES * PAGE_SIZE);
+    assert!(shm >= 0);
+    let shm = shm as usize;
+    assert_eq!(cap_identify(shm), CAP_TYPE_SHARED_MEMORY);
+    let rw = shm_map(CAP_ADDRESS_SPACE, shm, 0, PROT_READ | PROT_WRITE);
+    assert!(rw > 0);
+    let rw = rw as usize;
+    assert!(words(rw).iter().all(|&word| word == 0));
+
+    // A second view of the same frames sees every write through the first one.
+    let view = shm_map(CAP_ADDRESS_SPACE, shm, 0, PROT_READ | PROT_WRITE);
+    assert!(view > 0 && view as usize != rw);
+    let view = view as usize;
+    for (i, word) in words(rw).iter_mut().enumerate() {
+        *word = i;
+    }
+    assert!(words(view).iter().enumerate().all(|(i, &word)| word == i));
+    println!("two views ok.");
+
+    // Forked children share the mapping instead of getting a copy.
+    assert_eq!(in_child(|| words(rw)[0] = 0xdead), 0);
+    assert_eq!(words(view)[0], 0xdead);
+    println!("shared across fork ok.");
+
+    // A read-only capability maps read-only, and mprotect cannot get around it.
+    assert_eq!(cap_mint(shm, READ_ONLY, CAP_READ, 0), 0);
+    assert!(shm_map(CAP_ADDRESS_SPACE, READ_ONLY, 0, PROT_READ | PROT_WRITE) < 0);
+    let ro = shm_map(CAP_ADDRESS_SPACE, READ_ONLY, 0, PROT_READ);
+    assert!(ro > 0);
+    let ro = ro as usize;
+    assert_eq!(words(ro)[0], 0xdead);
+    assert!(mprotect(ro, PAGE_SIZE, PROT_READ | PROT_WRITE) < 0);
+    assert_eq!(in_child(|| words(ro)[0] = 0), -2);
+    assert_eq!(mprotect(view, PAGE_SIZE, PROT_READ), 0);
+    assert_eq!(mprotect(view, PAGE_SIZE, PROT_READ | PROT_WRITE), 0);
+    println!("read-only view ok.");
+
+    // Unmapping some views leaves the others intact.
+    assert_eq!(munmap(rw, PAGES * PAGE_SIZE), 0);
+    assert_eq!(munmap(view, PAGES * PAGE_SIZE), 0);
+    assert_eq!(words(ro)[1], 1);
+    assert_eq!(munmap(ro, PAGES * PAGE_SIZE), 0);
+    println!("shm_test passed!");
+    0
+}
@@ -0,0 +1,96 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{exit, fork, getpid, kill, sigaction, sigmask, sigprocmask, sleep, waitpid, yield_, SignalAction, SIGKILL, SIGSEGV, SIGTERM, SIGUSR1, SIGUSR2, SIG_BLOCK, SIG_UNBLOCK, EPERM};
+
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn count_signal(signum: usize) {
+    HANDLED.fetch_add(signum, Ordering::Relaxed);
+}
+
+extern "C" fn exit_on_fault(signum: usize) {
+    exit(signum as i32 * 10);
+}
+
+/// Runs `f` in a child process, sends the child `signum` after `delay_ms` unless that is
+/// None, and returns the child's exit code.
+fn in_child(f: fn(), signal: Option<(usize, usize)>) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    if let Some((signum, delay_ms)) = signal {
+        sleep(delay_ms);
+        assert_eq!(kill(pid as usize, signum), 0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+fn handler_runs_and_returns() {
+    let action = SignalAction::new(count_signal, 0);
+    assert_eq!(sigaction(SIGUSR1, Some(&action), None), 0);
+    let before = [1usize, 2, 3];
+    assert_eq!(kill(getpid() as usize, SIGUSR1), 0);
+    // The handler ran on the way back from kill, and the interrupted code carries on intact.
+    assert_eq!(HANDLED.load(Ordering::Relaxed), SIGUSR1);
+    assert_eq!(before, [1, 2, 3]);
+    let mut old = SignalAction::default();
+    assert_eq!(sigaction(SIGUSR1, None, Some(&mut old)), 0);
+    assert_eq!(old.handler, count_signal as *const () as usize);
+}
+
+fn masked_signals_wait() {
+    HANDLED.store(0, Ordering::Relaxed);
+    assert_eq!(sigprocmask(SIG_BLOCK, sigmask(SIGUSR1)), 0);
+    kill(getpid() as usize, SIGUSR1);
+    yield_();
+    assert_eq!(HANDLED.load(Ordering::Relaxed), 0);
+    assert_eq!(sigprocmask(SIG_UNBLOCK, sigmask(SIGUSR1)), sigmask(SIGUSR1) as isize);
+    assert_eq!(HANDLED.load(Ordering::Relaxed), SIGUSR1);
+}
+
+fn ignored_and_default_actions() {
+    assert_eq!(sigaction(SIGUSR2, Some(&SignalAction::ignore()), None), 0);
+    kill(getpid() as usize, SIGUSR2);
+    assert!(sigaction(SIGKILL, Some(&SignalAction::ignore()), None) < 0);
+    // A busy child, and one that is blocked, are both stopped by a signal from outside.
+    assert_eq!(in_child(|| loop { core::hint::spin_loop() }, Some((SIGKILL, 10))), -(SIGKILL as i32));
+    assert_eq!(in_child(|| sleep(100_000), Some((SIGTERM, 10))), -(SIGTERM as i32));
+}
+
+fn faults_become_signals() {
+    fn write_null() {
+        unsafe { core::ptr::null_mut::<usize>().write_volatile(1) };
+    }
+    assert_eq!(in_child(write_null, None), -(SIGSEGV as i32));
+    assert_eq!(in_child(|| {
+        sigaction(SIGSEGV, Some(&SignalAction::new(exit_on_fault, 0)), None);
+        write_null();
+    }, None), SIGSEGV as i32 * 10);
+}
+
+fn initproc_survives() {
+    const INITPROC_PID: usize = 1;
+    assert_eq!(kill(INITPROC_PID, SIGKILL), EPERM);
+    assert_eq!(kill(INITPROC_PID, SIGTERM), EPERM);
+    assert_eq!(kill(INITPROC_PID, 0), 0);
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    handler_runs_and_returns();
+    masked_signals_wait();
+    ignored_and_default_actions();
+    faults_become_signals();
+    initproc_survives();
+    println!("signal_test passed!");
+    0
+}
@@ -4,7 +4,7 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{exit, fork, get_time, sleep, waitpid};
+use user_lib::{exit, fork, get_idle_time, get_time, sleep, waitpid};
 
 fn sleepy() {
     let time: usize = 100;
@@ -18,6 +18,7 @@ fn sleepy() {
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
     let current_time = get_time();
+    let idle_time = get_idle_time(0);
     let pid = fork();
     let mut exit_code: i32 = 0;
     if pid == 0 {
@@ -25,6 +26,9 @@ pub fn main() -> i32 {
     }
     assert!(waitpid(pid as usize, &mut exit_code) == pid && exit_code == 0);
     println!("use {} msecs.", get_time() - current_time);
+    let idle_ms = (get_idle_time(0) - idle_time) / 1000;
+    println!("hart 0 idled for {} msecs.", idle_ms);
+    assert!(idle_ms > 0);
     println!("sleep pass.");
     0
 }
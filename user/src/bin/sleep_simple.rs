@@ -4,7 +4,7 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{get_time, sleep};
+use user_lib::{get_time, get_time_us, sleep, sleep_us};
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
@@ -18,6 +18,12 @@ pub fn main() -> i32 {
         end,
         end - start
     );
+    assert!(end - start >= 100);
+    let start_us = get_time_us();
+    sleep_us(500);
+    let delta_us = get_time_us() - start_us;
+    println!("delta = {}us after sleeping 500us!", delta_us);
+    assert!(delta_us >= 500);
     println!("r_sleep passed!");
     0
 }
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exec, fork, get_sched_stat, waitpid, SchedStat};
+
+const MAX_HARTS: usize = 8;
+
+/// Reads the counters of every running hart. Returns how many there are.
+fn read_stats(stats: &mut [SchedStat; MAX_HARTS]) -> usize {
+    let mut harts = 0;
+    while harts < MAX_HARTS && get_sched_stat(harts, &mut stats[harts]) == 0 {
+        harts += 1;
+    }
+    harts
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut before = [SchedStat::default(); MAX_HARTS];
+    let harts = read_stats(&mut before);
+    if harts < 2 {
+        println!("smp_test skipped, only one hart is running");
+        return 0;
+    }
+    let pid = fork();
+    if pid == 0 {
+        exec("forktree\0");
+        panic!("unreachable!");
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    let mut after = [SchedStat::default(); MAX_HARTS];
+    assert_eq!(read_stats(&mut after), harts);
+    for hart in 0..harts {
+        let switches = after[hart].switches - before[hart].switches;
+        let migrations = after[hart].migrations - before[hart].migrations;
+        println!("hart {}: {} switches, {} migrations", hart, switches, migrations);
+        assert!(switches > 0, "hart {} ran nothing", hart);
+    }
+    println!("smp_test passed!");
+    0
+}
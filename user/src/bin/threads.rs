@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{exit, gettid, thread_create, waittid};
+
+const THREADS: usize = 8;
+const CHUNK: usize = 10000;
+
+/// Each thread stores the sum of its own chunk here, and adds it to `TOTAL`.
+static SUMS: [AtomicUsize; THREADS] = [const { AtomicUsize::new(0) }; THREADS];
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+fn sum_chunk(index: usize) -> ! {
+    let sum: usize = (index * CHUNK..(index + 1) * CHUNK).sum();
+    SUMS[index].store(sum, Ordering::Relaxed);
+    TOTAL.fetch_add(sum, Ordering::Relaxed);
+    exit(gettid() as i32)
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert_eq!(gettid(), 0);
+    let mut tids = [0; THREADS];
+    for (index, tid) in tids.iter_mut().enumerate() {
+        let created = thread_create(sum_chunk, index);
+        assert!(created > 0);
+        *tid = created as usize;
+    }
+    for tid in tids {
+        assert_eq!(waittid(tid), tid as isize);
+    }
+    // A thread that was joined once cannot be joined again.
+    assert!(waittid(tids[0]) < 0);
+    assert!(waittid(0) < 0);
+    for (index, sum) in SUMS.iter().enumerate() {
+        let expected: usize = (index * CHUNK..(index + 1) * CHUNK).sum();
+        assert_eq!(sum.load(Ordering::Relaxed), expected);
+    }
+    let n = THREADS * CHUNK;
+    assert_eq!(TOTAL.load(Ordering::Relaxed), n * (n - 1) / 2);
+    println!("threads passed!");
+    0
+}
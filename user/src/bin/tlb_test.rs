@@ -0,0 +1,68 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{exit, fork, get_time, mmap, mprotect, munmap, thread_create, waitpid, yield_, PROT_READ, PROT_WRITE};
+
+const PAGE_SIZE: usize = 4096;
+const START: usize = 0x10_0000_0000;
+/// Enough writers to keep the other harts busy in user mode.
+const WRITERS: usize = 3;
+const WAIT_MS: isize = 1000;
+
+static STARTED: AtomicUsize = AtomicUsize::new(0);
+/// Writes that made it to the page, counted outside of it.
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+fn counter() -> &'static AtomicUsize {
+    unsafe { &*(START as *const AtomicUsize) }
+}
+
+fn writer(_: usize) -> ! {
+    STARTED.fetch_add(1, Ordering::SeqCst);
+    loop {
+        counter().fetch_add(1, Ordering::Relaxed);
+        WRITES.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Takes write access to the page away while writers run on other harts, then checks that
+/// none of them kept writing through a stale TLB entry. The first writer to notice faults,
+/// which kills the process. Each writer may still count one write it made before.
+fn revoke_under_writers(revoke: fn() -> isize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(mmap(START, PAGE_SIZE, PROT_READ | PROT_WRITE), START as isize);
+        for _ in 0..WRITERS {
+            thread_create(writer, 0);
+        }
+        while STARTED.load(Ordering::SeqCst) < WRITERS {
+            yield_();
+        }
+        assert_eq!(revoke(), 0);
+        let writes = WRITES.load(Ordering::SeqCst);
+        let start = get_time();
+        while get_time() - start < WAIT_MS {
+            if WRITES.load(Ordering::SeqCst) > writes + WRITERS {
+                exit(1);
+            }
+        }
+        exit(0);
+    }
+    let mut exit_code: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    exit_code
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    assert_eq!(revoke_under_writers(|| mprotect(START, PAGE_SIZE, PROT_READ)), -2);
+    println!("mprotect stopped every writer.");
+    assert_eq!(revoke_under_writers(|| munmap(START, PAGE_SIZE)), -2);
+    println!("munmap stopped every writer.");
+    println!("tlb_test passed!");
+    0
+}
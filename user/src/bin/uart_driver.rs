@@ -0,0 +1,102 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::string::String;
+use user_lib::{irq_ack, irq_handler_get, irq_handler_set_notification, mmio_map, notification_create, wait_notification, CAP_ADDRESS_SPACE, CAP_IRQ_CONTROL};
+
+const UART_BASE_ADDR: usize = 0x1000_0000;
+const UART_IRQ: usize = 10;
+
+// Registers of the 16550 and the line status bits we need.
+const RBR: usize = 0;
+const THR: usize = 0;
+const LSR: usize = 5;
+const LSR_DATA_READY: u8 = 1 << 0;
+const LSR_THR_EMPTY: u8 = 1 << 5;
+
+const LF: u8 = b'\n';
+const CR: u8 = b'\r';
+const BS: u8 = 0x08;
+const DEL: u8 = 0x7f;
+
+struct Uart {
+    base: usize
+}
+
+impl Uart {
+    fn reg(&self, offset: usize) -> *mut u8 {
+        (self.base + offset) as *mut u8
+    }
+    fn recv(&self) -> Option<u8> {
+        unsafe {
+            if self.reg(LSR).read_volatile() & LSR_DATA_READY == 0 {
+                return None;
+            }
+            Some(self.reg(RBR).read_volatile())
+        }
+    }
+    fn send(&self, data: u8) {
+        unsafe {
+            while self.reg(LSR).read_volatile() & LSR_THR_EMPTY == 0 {}
+            self.reg(THR).write_volatile(data);
+        }
+    }
+}
+
+/// Takes console input over from the kernel and echoes every line back until it reads
+/// "exit", after which the kernel driver gets the UART back. initproc starts it at boot,
+/// handing it the only `CAP_IRQ_CONTROL`.
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let handler = irq_handler_get(CAP_IRQ_CONTROL, UART_IRQ);
+    let notification = notification_create();
+    if handler < 0 || notification < 0 {
+        println!("uart_driver: no capabilities for the UART.");
+        return -1;
+    }
+    let (handler, notification) = (handler as usize, notification as usize);
+    if irq_handler_set_notification(handler, notification) != 0 {
+        println!("uart_driver: the UART interrupt is taken.");
+        return -1;
+    }
+    let base = mmio_map(CAP_IRQ_CONTROL, CAP_ADDRESS_SPACE, UART_BASE_ADDR);
+    if base < 0 {
+        println!("uart_driver: cannot map the UART.");
+        return -1;
+    }
+    let uart = Uart { base: base as usize };
+    println!("uart_driver: serving console input, type \"exit\" to quit.");
+    let mut line = String::new();
+    loop {
+        assert!(wait_notification(notification) > 0);
+        while let Some(data) = uart.recv() {
+            match data {
+                LF | CR => {
+                    uart.send(CR);
+                    uart.send(LF);
+                    if line == "exit" {
+                        return 0;
+                    }
+                    println!("uart_driver: {}", line);
+                    line.clear();
+                }
+                BS | DEL => {
+                    if line.pop().is_some() {
+                        uart.send(BS);
+                        uart.send(b' ');
+                        uart.send(BS);
+                    }
+                }
+                _ => {
+                    line.push(data as char);
+                    uart.send(data);
+                }
+            }
+        }
+        irq_ack(handler);
+    }
+}
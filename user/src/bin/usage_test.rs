@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{exit, fork, get_time_us, getrusage, sleep, times, waitpid, Rusage, Tms, RUSAGE_CHILDREN, RUSAGE_SELF};
+
+const SPIN_US: isize = 200_000;
+
+fn spin() {
+    let start = get_time_us();
+    while get_time_us() - start < SPIN_US {
+        core::hint::spin_loop();
+    }
+}
+
+#[unsafe(no_mangle)]
+pub fn main() -> i32 {
+    let mut usage = Rusage::default();
+    assert_eq!(getrusage(2, &mut usage), -22);
+
+    spin();
+    sleep(10);
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    let busy = usage.ru_utime.as_us() + usage.ru_stime.as_us();
+    println!("usage_test: self {}us busy, {} voluntary switches", busy, usage.ru_nvcsw);
+    // Some of the spinning may have been lost to other processes, but most of it is ours.
+    assert!(busy >= SPIN_US as usize / 2);
+    assert!(usage.ru_nvcsw >= 1);
+
+    let pid = fork();
+    if pid == 0 {
+        spin();
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut usage), 0);
+    let child_busy = usage.ru_utime.as_us() + usage.ru_stime.as_us();
+    assert!(child_busy >= SPIN_US as usize / 2);
+
+    let mut tms = Tms::default();
+    assert!(times(&mut tms) > 0);
+    assert!(tms.tms_utime + tms.tms_stime > 0);
+    assert!(tms.tms_cutime + tms.tms_cstime > 0);
+    println!("usage_test passed!");
+    0
+}
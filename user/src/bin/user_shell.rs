@@ -6,63 +6,60 @@ extern crate alloc;
 #[macro_use]
 extern crate user_lib;
 
-const LF: u8 = 0x0au8;
-const CR: u8 = 0x0du8;
-const DL: u8 = 0x7fu8;
-const BS: u8 = 0x08u8;
-
 use alloc::string::String;
-use user_lib::{exec, fork, shutdown, waitpid};
-use user_lib::console::getchar;
+use user_lib::{exec, fork, getrusage, shutdown, tty_set_foreground, waitpid, Rusage, RUSAGE_CHILDREN};
+use user_lib::console::read_line;
 
 #[unsafe(no_mangle)]
 pub fn main() -> i32 {
     println!("Rust user shell");
     let mut line: String = String::new();
-    print!(">> ");
     loop {
-        let c = getchar();
-        match c {
-            LF | CR => {
-                println!("");
-                if !line.is_empty() {
-                    line.push('\0');
-                    if line == "shutdown\0" {
-                        shutdown(0);
-                    }
-                    let pid = fork();
-                    if pid == 0 {
-                        if exec(line.as_str()) == -1 {
-                            println!("Error when executing!");
-                            return -4;
-                        }
-                        unreachable!();
-                    } else {
-                        let mut exit_code: i32 = 0;
-                        let exit_pid = waitpid(pid as usize, &mut exit_code);
-                        assert_eq!(pid, exit_pid);
-                        println!(
-                            "Shell: Process {} exited with code {}",
-                            pid,
-                            exit_code
-                        );
-                    }
-                    line.clear();
-                }
-                print!(">> ");
-            }
-            BS | DL => {
-                if !line.is_empty() {
-                    print!("{}", BS as char);
-                    print!(" ");
-                    print!("{}", BS as char);
-                    line.pop();
-                }
-            }
-            _ => {
-                print!("{}", c as char);
-                line.push(c as char);
+        print!(">> ");
+        // The terminal echoes and edits the line, Ctrl-D on an empty line ends the input.
+        if !read_line(&mut line) {
+            println!("");
+            shutdown(0);
+        }
+        if line.is_empty() {
+            continue;
+        }
+        line.push('\0');
+        if line == "shutdown\0" {
+            shutdown(0);
+        }
+        let pid = fork();
+        if pid == 0 {
+            if exec(line.as_str()) == -1 {
+                println!("Error when executing!");
+                return -4;
             }
+            unreachable!();
+        } else {
+            // Ctrl-C interrupts the command while it runs.
+            tty_set_foreground(pid as usize);
+            let mut exit_code: i32 = 0;
+            let mut before = Rusage::default();
+            getrusage(RUSAGE_CHILDREN, &mut before);
+            let exit_pid = waitpid(pid as usize, &mut exit_code);
+            tty_set_foreground(0);
+            // The usage of the children reaped so far grew by what the command used.
+            let mut after = Rusage::default();
+            getrusage(RUSAGE_CHILDREN, &mut after);
+            assert_eq!(pid, exit_pid);
+            println!(
+                "Shell: Process {} exited with code {}",
+                pid,
+                exit_code
+            );
+            println!(
+                "Shell: user {}us, sys {}us, {} voluntary and {} involuntary switches, {} page faults",
+                after.ru_utime.as_us() - before.ru_utime.as_us(),
+                after.ru_stime.as_us() - before.ru_stime.as_us(),
+                after.ru_nvcsw - before.ru_nvcsw,
+                after.ru_nivcsw - before.ru_nivcsw,
+                after.ru_minflt - before.ru_minflt
+            );
         }
     }
-}
\ No newline at end of file
+}
@@ -5,24 +5,40 @@
 extern crate user_lib;
 
 // not in SUCC_TESTS & FAIL_TESTS
-// count_lines, infloop, user_shell, usertests
+// count_lines, echo_server, infloop, name_server, uart_driver, user_shell, usertests
 
 // item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
+    ("cap_test\0", "\0", "\0", "\0", 0),
+    ("echo_client\0", "\0", "\0", "\0", 0),
+    ("notification_test\0", "\0", "\0", "\0", 0),
+    ("shm_test\0", "\0", "\0", "\0", 0),
+    ("threads\0", "\0", "\0", "\0", 0),
+    ("philosophers\0", "\0", "\0", "\0", 0),
+    ("producer_consumer\0", "\0", "\0", "\0", 0),
+    ("futex_test\0", "\0", "\0", "\0", 0),
+    ("signal_test\0", "\0", "\0", "\0", 0),
+    ("priority_test\0", "\0", "\0", "\0", 0),
+    ("usage_test\0", "\0", "\0", "\0", 0),
+    ("tlb_test\0", "\0", "\0", "\0", 0),
+    ("smp_test\0", "\0", "\0", "\0", 0),
+    ("name_service_test\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
     ("forktest\0", "\0", "\0", "\0", 0),
     ("forktest2\0", "\0", "\0", "\0", 0),
     ("forktree\0", "\0", "\0", "\0", 0),
+    ("heap_test\0", "\0", "\0", "\0", 0),
     ("hello_world\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
+    ("mmap_test\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
 ];
 
-static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[("stack_overflow\0", "\0", "\0", "\0", -2)];
+static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[("stack_overflow\0", "\0", "\0", "\0", -11)];
 
 use user_lib::{exec, fork, waitpid};
 
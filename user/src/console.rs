@@ -1,3 +1,4 @@
+use alloc::string::String;
 use core::fmt::{self, Write};
 
 const STDIN: usize = 0;
@@ -44,3 +45,24 @@ pub fn getchar() -> u8 {
     read(STDIN, &mut c);
     c[0]
 }
+
+/// Reads one line from the terminal, which does the editing and echoing, into `line`
+/// without its line feed. Returns false at end of input.
+pub fn read_line(line: &mut String) -> bool {
+    line.clear();
+    let mut buf = [0u8; 128];
+    loop {
+        let len = read(STDIN, &mut buf);
+        if len <= 0 {
+            return !line.is_empty();
+        }
+        let data = &buf[..len as usize];
+        match data.split_last() {
+            Some((b'\n', rest)) => {
+                line.push_str(&String::from_utf8_lossy(rest));
+                return true;
+            }
+            _ => line.push_str(&String::from_utf8_lossy(data))
+        }
+    }
+}
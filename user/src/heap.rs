@@ -0,0 +1,51 @@
+use buddy_system_allocator::{Heap, LockedHeap};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{null_mut, NonNull};
+use crate::sbrk;
+
+/// The heap grows by at least this many bytes at a time.
+const HEAP_CHUNK_SIZE: usize = 16384;
+
+/// A buddy heap living above the program break, extended through `sbrk` whenever an
+/// allocation does not fit.
+pub struct GrowableHeap(LockedHeap);
+
+impl GrowableHeap {
+    pub const fn empty() -> Self {
+        Self(LockedHeap::empty())
+    }
+
+    /// Extends the heap with a naturally aligned block that can hold `layout`.
+    fn grow(heap: &mut Heap, layout: &Layout) -> bool {
+        let block = layout.size()
+            .max(layout.align())
+            .next_power_of_two()
+            .max(HEAP_CHUNK_SIZE);
+        let brk = sbrk(0) as usize;
+        let end = brk.next_multiple_of(block) + block;
+        if sbrk((end - brk) as isize) < 0 {
+            return false;
+        }
+        unsafe { heap.add_to_heap(brk, end); }
+        true
+    }
+}
+
+unsafe impl GlobalAlloc for GrowableHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        if !Self::grow(&mut heap, &layout) {
+            return null_mut();
+        }
+        heap.alloc(layout).map_or(null_mut(), |ptr| ptr.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe {
+            self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout);
+        }
+    }
+}
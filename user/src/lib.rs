@@ -2,21 +2,123 @@
 #![feature(linkage)]
 #![feature(alloc_error_handler)]
 
+extern crate alloc;
+
 #[macro_use]
 pub mod console;
+mod heap;
 mod lang_items;
+pub mod service;
+pub mod sync;
 mod syscall;
 
-use buddy_system_allocator::LockedHeap;
-use core::ptr::addr_of_mut;
+use core::sync::atomic::AtomicU32;
+use heap::GrowableHeap;
+pub use service::{lookup_service, register_service};
+pub use sync::{FutexMutex, FutexMutexGuard, Mutex, MutexGuard};
 use syscall::*;
 
-const USER_HEAP_SIZE: usize = 16384;
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+
+/// Seconds and nanoseconds, as used by `nanosleep` and `clock_gettime`.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize
+}
+
+/// Seconds and microseconds, as used by `getrusage`.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize
+}
+
+impl TimeVal {
+    pub fn as_us(&self) -> usize {
+        self.sec * 1_000_000 + self.usec
+    }
+}
+
+/// Resource usage reported by `getrusage`, laid out like Linux's. The kernel only fills in
+/// the times, `ru_minflt` and the context switch counts.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Rusage {
+    pub ru_utime: TimeVal,
+    pub ru_stime: TimeVal,
+    pub ru_maxrss: usize,
+    pub ru_ixrss: usize,
+    pub ru_idrss: usize,
+    pub ru_isrss: usize,
+    pub ru_minflt: usize,
+    pub ru_majflt: usize,
+    pub ru_nswap: usize,
+    pub ru_inblock: usize,
+    pub ru_oublock: usize,
+    pub ru_msgsnd: usize,
+    pub ru_msgrcv: usize,
+    pub ru_nsignals: usize,
+    pub ru_nvcsw: usize,
+    pub ru_nivcsw: usize
+}
+
+/// CPU times reported by `times`, in `CLOCKS_PER_SEC` ticks.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct Tms {
+    pub tms_utime: usize,
+    pub tms_stime: usize,
+    pub tms_cutime: usize,
+    pub tms_cstime: usize
+}
+
+/// Scheduling counters of a hart, see `get_sched_stat`.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SchedStat {
+    /// Threads the hart switched to.
+    pub switches: usize,
+    /// Switches to a thread that ran on another hart last.
+    pub migrations: usize
+}
 
-static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
+/// Well-known capability slots.
+pub const CAP_ADDRESS_SPACE: usize = 0;
+pub const CAP_IRQ_CONTROL: usize = 1;
+pub const CAP_NAME_SERVER: usize = 2;
+/// Number of capability slots of every process, as configured in the kernel.
+pub const CSPACE_SLOTS: usize = 64;
+
+/// Slot number meaning that no capability is transferred with a message.
+pub const NO_CAP: usize = usize::MAX;
+
+/// Capability rights.
+pub const CAP_READ: usize = 1 << 0;
+pub const CAP_WRITE: usize = 1 << 1;
+
+/// Capability types reported by `cap_identify`.
+pub const CAP_TYPE_ENDPOINT: isize = 1;
+pub const CAP_TYPE_FRAME: isize = 2;
+pub const CAP_TYPE_ADDRESS_SPACE: isize = 3;
+pub const CAP_TYPE_IRQ_CONTROL: isize = 4;
+pub const CAP_TYPE_IRQ_HANDLER: isize = 5;
+pub const CAP_TYPE_NOTIFICATION: isize = 6;
+pub const CAP_TYPE_SHARED_MEMORY: isize = 7;
+
+/// What `recv` returns when it was woken by the bound notification instead of a message.
+/// The notification word is then in the first message word.
+pub const NOTIFICATION_BADGE: isize = isize::MAX;
+
+/// A message of four words passed in registers by the IPC syscalls.
+pub type IpcMessage = [usize; 4];
 
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: GrowableHeap = GrowableHeap::empty();
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
@@ -26,10 +128,6 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 #[unsafe(no_mangle)]
 #[unsafe(link_section = ".text.entry")]
 pub extern "C" fn _start() -> ! {
-    unsafe {
-        HEAP.lock()
-            .init(addr_of_mut!(HEAP_SPACE) as usize, USER_HEAP_SIZE);
-    }
     exit(main());
 }
 
@@ -39,9 +137,30 @@ fn main() -> i32 {
     panic!("Cannot find main!");
 }
 
+/// Returned when a buffer passed to the kernel is not mapped for the access the call makes.
+pub const EFAULT: isize = -14;
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
+/// Local mode flags of the terminal, see `tty_set_mode`.
+pub const ISIG: usize = 1 << 0;
+pub const ICANON: usize = 1 << 1;
+pub const ECHO: usize = 1 << 3;
+
+pub fn tty_get_mode() -> usize {
+    let mut mode = 0;
+    sys_ioctl(0, TCGETS, &mut mode as *mut usize as usize);
+    mode
+}
+/// Without `ICANON` reads return bytes as soon as they arrive instead of whole lines.
+/// Without `ISIG` Ctrl-C is read like any other byte.
+pub fn tty_set_mode(mode: usize) -> isize {
+    sys_ioctl(0, TCSETS, mode)
+}
+/// Makes Ctrl-C send SIGINT to process `pid`, or to no process if it is 0.
+pub fn tty_set_foreground(pid: usize) -> isize {
+    sys_ioctl(0, TIOCSPGRP, pid)
+}
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
@@ -63,34 +182,356 @@ pub fn fork() -> isize {
 pub fn exec(path: &str) -> isize {
     sys_exec(path)
 }
-pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
+/// Starts a thread running `entry(arg)` and returns its tid. `entry` must not return; it
+/// ends with `exit`, which ends the whole process only when called from the first thread.
+pub fn thread_create(entry: fn(usize) -> !, arg: usize) -> isize {
+    sys_thread_create(entry as usize, arg)
+}
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+/// Blocks until thread `tid` exits and returns its exit code.
+pub fn waittid(tid: usize) -> isize {
+    sys_waittid(tid)
+}
+/// Creates a mutex that makes waiting threads yield in a loop and returns its id.
+pub fn mutex_create() -> isize {
+    sys_mutex_create(false)
+}
+/// Creates a mutex that puts waiting threads to sleep and returns its id.
+pub fn mutex_blocking_create() -> isize {
+    sys_mutex_create(true)
+}
+pub fn mutex_lock(id: usize) -> isize {
+    sys_mutex_lock(id)
+}
+pub fn mutex_unlock(id: usize) -> isize {
+    sys_mutex_unlock(id)
+}
+/// Creates a semaphore holding `count` units and returns its id.
+pub fn semaphore_create(count: usize) -> isize {
+    sys_semaphore_create(count)
+}
+pub fn semaphore_up(id: usize) -> isize {
+    sys_semaphore_up(id)
+}
+pub fn semaphore_down(id: usize) -> isize {
+    sys_semaphore_down(id)
+}
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+pub fn condvar_signal(id: usize) -> isize {
+    sys_condvar_signal(id)
+}
+/// Releases mutex `mutex_id`, which the caller holds, until condition variable `id` is
+/// signalled.
+pub fn condvar_wait(id: usize, mutex_id: usize) -> isize {
+    sys_condvar_wait(id, mutex_id)
+}
+/// Returned by `futex_wait` when the word no longer held the expected value.
+pub const EAGAIN: isize = -11;
+/// Returned by `futex_wait` when the timeout passed first.
+pub const ETIMEDOUT: isize = -110;
+/// Sleeps while `word` holds `val`, for at most `timeout`. Returns 0 once woken by
+/// `futex_wake`, which may be spurious, so callers check the word again.
+pub fn futex_wait(word: &AtomicU32, val: u32, timeout: Option<&TimeSpec>) -> isize {
+    let timeout = timeout.map_or(core::ptr::null(), |timeout| timeout as *const TimeSpec);
+    sys_futex(word, FUTEX_WAIT, val as usize, timeout)
+}
+/// Wakes up to `count` threads sleeping on `word`, in this or any other process that maps
+/// it. Returns how many it woke.
+pub fn futex_wake(word: &AtomicU32, count: usize) -> isize {
+    sys_futex(word, FUTEX_WAKE, count, core::ptr::null())
+}
+pub const SIGINT: usize = 2;
+pub const SIGILL: usize = 4;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+
+/// Handler values with a special meaning.
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+/// Ways `sigprocmask` can change the mask.
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+/// A set of signals with bit `n` standing for signal `n`.
+pub const fn sigmask(signum: usize) -> u32 {
+    1 << signum
+}
+
+/// What to do with a signal. A handler is called with the signal number, and `mask` is
+/// blocked on top of the signal itself while it runs.
+#[repr(C)]
+#[derive(Copy, Clone, Default)]
+pub struct SignalAction {
+    pub handler: usize,
+    restorer: usize,
+    pub mask: u32
+}
+
+impl SignalAction {
+    pub fn new(handler: extern "C" fn(usize), mask: u32) -> Self {
+        Self { handler: handler as *const () as usize, restorer: 0, mask }
+    }
+    pub fn ignore() -> Self {
+        Self { handler: SIG_IGN, ..Self::default() }
     }
 }
 
+/// Where handlers return to, to leave the handler and resume the interrupted code.
+extern "C" fn sigreturn_restorer() -> ! {
+    sys_sigreturn();
+    unreachable!();
+}
+
+/// Returned by `kill` for a signal that would end initproc.
+pub const EPERM: isize = -1;
+/// Sends `signum` to process `pid`. A process that a signal terminates exits with code
+/// `-signum`.
+pub fn kill(pid: usize, signum: usize) -> isize {
+    sys_kill(pid, signum)
+}
+/// Installs `action` for `signum`, returning the previous action in `old_action`.
+pub fn sigaction(signum: usize, action: Option<&SignalAction>, old_action: Option<&mut SignalAction>) -> isize {
+    let action = action.map(|action| SignalAction { restorer: sigreturn_restorer as *const () as usize, ..*action });
+    sys_sigaction(
+        signum,
+        action.as_ref().map_or(core::ptr::null(), |action| action as *const _),
+        old_action.map_or(core::ptr::null_mut(), |action| action as *mut _)
+    )
+}
+/// Changes the set of masked signals as `how` says and returns the previous set.
+pub fn sigprocmask(how: usize, set: u32) -> isize {
+    sys_sigprocmask(how, set)
+}
+/// Returned by `set_priority` and `nice` when the kernel's scheduler ignores priorities.
+pub const ENOSYS: isize = -38;
+/// Priority of a thread whose nice value is 0.
+pub const DEFAULT_PRIORITY: isize = 1024;
+/// Sets the calling thread's share of the CPU relative to other threads, `DEFAULT_PRIORITY`
+/// being the normal share. Returns `prio`.
+pub fn set_priority(prio: isize) -> isize {
+    sys_set_priority(prio)
+}
+/// Adds `inc` to the calling thread's nice value, between -20 and 19, and returns the new
+/// value. Every step up gives the thread about 10% less of the CPU.
+pub fn nice(inc: isize) -> isize {
+    sys_nice(inc)
+}
+/// Makes `waitpid_options` return -2 instead of blocking while the child is still running.
+pub const WNOHANG: usize = 1;
+
+/// Blocks until any child exits. Returns its pid, or -1 if there are no children.
+pub fn wait(exit_code: &mut i32) -> isize {
+    sys_waitpid(-1, exit_code as *mut _, 0)
+}
+
+/// Blocks until the child `pid` exits. Returns `pid`, or -1 if there is no such child.
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
-    }
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
+}
+
+pub fn waitpid_options(pid: isize, exit_code: &mut i32, options: usize) -> isize {
+    sys_waitpid(pid, exit_code as *mut _, options)
+}
+/// Microseconds since boot.
+pub fn get_time_us() -> isize {
+    let mut time = TimeSpec::default();
+    sys_clock_gettime(&mut time);
+    (time.sec * 1_000_000 + time.nsec / 1_000) as isize
+}
+/// Ticks per second of the clock used by `times`.
+pub const CLOCKS_PER_SEC: usize = 100;
+/// Fills `tms` with the CPU time of the caller and of its reaped children. Returns the ticks
+/// since boot.
+pub fn times(tms: &mut Tms) -> isize {
+    sys_times(tms)
+}
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+/// Fills `usage` with what the caller or, for `RUSAGE_CHILDREN`, its reaped children used.
+pub fn getrusage(who: isize, usage: &mut Rusage) -> isize {
+    sys_getrusage(who, usage)
+}
+/// Microseconds `hart` has spent idle since boot.
+pub fn get_idle_time(hart: usize) -> isize {
+    sys_get_idle_time(hart)
+}
+/// Scheduling counters of `hart` since boot. Fails for harts that are not running.
+pub fn get_sched_stat(hart: usize, stat: &mut SchedStat) -> isize {
+    sys_get_sched_stat(hart, stat)
+}
+/// Restricts the calling thread, or every thread of process `pid` unless it is 0, to the
+/// harts in the bit mask `mask`.
+pub fn sched_setaffinity(pid: usize, mask: usize) -> isize {
+    sys_sched_setaffinity(pid, &mask)
+}
+pub fn nanosleep(req: &TimeSpec) -> isize {
+    sys_nanosleep(req)
 }
 pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
-        sys_yield();
+    sleep_us(period_ms * 1_000);
+}
+pub fn sleep_us(period_us: usize) {
+    nanosleep(&TimeSpec {
+        sec: period_us / 1_000_000,
+        nsec: period_us % 1_000_000 * 1_000
+    });
+}
+
+/// Copies the capability in `src` into the empty slot `dest`.
+pub fn cap_copy(src: usize, dest: usize) -> isize {
+    sys_cap_copy(src, dest)
+}
+/// Copies the capability in `src` into `dest` with only `rights`, and sets the badge of an
+/// unbadged endpoint capability if `badge` is not 0.
+pub fn cap_mint(src: usize, dest: usize, rights: usize, badge: usize) -> isize {
+    sys_cap_mint(src, dest, rights, badge)
+}
+pub fn cap_move(src: usize, dest: usize) -> isize {
+    sys_cap_move(src, dest)
+}
+pub fn cap_delete(slot: usize) -> isize {
+    sys_cap_delete(slot)
+}
+/// Deletes every capability copied or minted from the one in `slot`, in every process.
+pub fn cap_revoke(slot: usize) -> isize {
+    sys_cap_revoke(slot)
+}
+/// Returns one of the `CAP_TYPE_*` values, or a negative error if `slot` is empty.
+pub fn cap_identify(slot: usize) -> isize {
+    sys_cap_identify(slot)
+}
+/// Allocates a physical frame and returns the slot of its capability.
+pub fn frame_alloc() -> isize {
+    sys_frame_alloc()
+}
+/// Maps the frame in slot `frame` at `addr` of the address space in slot `aspace`, or
+/// anywhere if `addr` is 0. Forked children share the mapping.
+pub fn frame_map(aspace: usize, frame: usize, addr: usize, prot: usize) -> isize {
+    sys_frame_map(aspace, frame, addr, prot)
+}
+/// Allocates `len` bytes of zeroed memory that can be mapped into several address spaces,
+/// and returns the slot of its capability.
+pub fn shm_create(len: usize) -> isize {
+    sys_shm_create(len)
+}
+/// Maps the shared memory in slot `shm` into the address space in slot `aspace`, at `addr`
+/// or anywhere if `addr` is 0, and returns the address. Forked children share the mapping,
+/// and `munmap` removes it.
+pub fn shm_map(aspace: usize, shm: usize, addr: usize, prot: usize) -> isize {
+    sys_shm_map(aspace, shm, addr, prot)
+}
+/// Gets a capability for handling interrupt source `irq`.
+pub fn irq_handler_get(control: usize, irq: usize) -> isize {
+    sys_irq_handler_get(control, irq)
+}
+/// Delivers the interrupts of the handler in slot `handler` to a notification. The source
+/// stays masked after each interrupt until `irq_ack`.
+pub fn irq_handler_set_notification(handler: usize, notification: usize) -> isize {
+    sys_irq_handler_set_notification(handler, notification)
+}
+pub fn irq_ack(handler: usize) -> isize {
+    sys_irq_ack(handler)
+}
+/// Maps the registers of the device at physical address `base` into the address space in
+/// slot `aspace` and returns their address.
+pub fn mmio_map(control: usize, aspace: usize, base: usize) -> isize {
+    sys_mmio_map(control, aspace, base)
+}
+/// Creates an IPC endpoint and returns the slot of its capability. Forked children inherit
+/// copies of all capabilities.
+pub fn endpoint_create() -> isize {
+    sys_endpoint_create()
+}
+/// Blocks until a receiver on the endpoint in `slot` has taken `msg`.
+pub fn send(slot: usize, msg: &IpcMessage) -> isize {
+    sys_send(slot, msg, NO_CAP)
+}
+/// Like `send`, and gives the receiver a copy of the capability in slot `cap`.
+pub fn send_cap(slot: usize, msg: &IpcMessage, cap: usize) -> isize {
+    sys_send(slot, msg, cap)
+}
+/// Blocks until a message arrives on the endpoint in `slot`. Returns the badge of the
+/// capability it was sent through, or `NOTIFICATION_BADGE`.
+pub fn recv(slot: usize, msg: &mut IpcMessage) -> isize {
+    recv_cap(slot, msg, &mut NO_CAP.clone())
+}
+/// Like `recv`, and sets `cap` to the slot of the capability that came with the message,
+/// or `NO_CAP`.
+pub fn recv_cap(slot: usize, msg: &mut IpcMessage, cap: &mut usize) -> isize {
+    sys_recv(slot, msg, cap)
+}
+/// Sends `msg` on the endpoint in `slot` and waits for the reply, which replaces `msg`.
+pub fn call(slot: usize, msg: &mut IpcMessage) -> isize {
+    call_cap(slot, msg, &mut NO_CAP.clone())
+}
+/// Like `call`, also sending the capability in slot `cap` unless it is `NO_CAP`. `cap` is
+/// then set to the slot of the capability that came with the reply, or `NO_CAP`.
+pub fn call_cap(slot: usize, msg: &mut IpcMessage, cap: &mut usize) -> isize {
+    sys_call(slot, msg, cap)
+}
+/// Answers the call received last.
+pub fn reply(msg: &IpcMessage) -> isize {
+    sys_reply(msg, NO_CAP)
+}
+/// Like `reply`, and gives the caller a copy of the capability in slot `cap`.
+pub fn reply_cap(msg: &IpcMessage, cap: usize) -> isize {
+    sys_reply(msg, cap)
+}
+/// Creates a notification and returns the slot of its capability.
+pub fn notification_create() -> isize {
+    sys_notification_create()
+}
+/// ORs `bits` and the capability's badge into the notification word. Never blocks.
+pub fn signal(slot: usize, bits: usize) -> isize {
+    sys_signal(slot, bits)
+}
+/// Blocks until the notification word is not 0, then returns and clears it.
+pub fn wait_notification(slot: usize) -> isize {
+    sys_wait(slot)
+}
+/// Returns and clears the notification word without blocking.
+pub fn poll(slot: usize) -> isize {
+    sys_poll(slot)
+}
+/// Makes `recv` on the endpoint also return when the notification is signalled.
+pub fn notification_bind(endpoint: usize, notification: usize) -> isize {
+    sys_notification_bind(endpoint, notification)
+}
+
+/// Sets the program break and returns the new break, which is left unchanged on failure.
+pub fn brk(addr: usize) -> isize {
+    sys_brk(addr)
+}
+/// Moves the program break by `increment` bytes and returns the old break, or -1 on failure.
+pub fn sbrk(increment: isize) -> isize {
+    let old_brk = sys_brk(0);
+    if increment == 0 {
+        return old_brk;
+    }
+    let new_brk = old_brk + increment;
+    if sys_brk(new_brk as usize) != new_brk {
+        return -1;
     }
+    old_brk
+}
+pub fn mmap(addr: usize, len: usize, prot: usize) -> isize {
+    sys_mmap(addr, len, prot)
+}
+pub fn munmap(addr: usize, len: usize) -> isize {
+    sys_munmap(addr, len)
+}
+pub fn mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    sys_mprotect(addr, len, prot)
 }
 
 pub fn shutdown(exit_code: i32) -> ! {
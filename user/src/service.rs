@@ -0,0 +1,57 @@
+//! Client side of the name server, which initproc starts and every process reaches through
+//! the endpoint in `CAP_NAME_SERVER`. Servers register an endpoint under a name of at most
+//! `NAME_LEN` bytes, and clients look it up to get a copy of the endpoint capability.
+
+use crate::{call_cap, IpcMessage, CAP_NAME_SERVER, NO_CAP};
+
+pub const NS_REGISTER: usize = 1;
+pub const NS_LOOKUP: usize = 2;
+
+/// Names are carried in the last three message words.
+pub const NAME_LEN: usize = 24;
+
+/// Errors returned by the name server, numbered like the kernel's.
+pub const NS_NOT_FOUND: isize = -2;
+pub const NS_EXISTS: isize = -17;
+pub const NS_INVALID: isize = -22;
+pub const NS_FULL: isize = -28;
+
+/// Packs a non-empty name into message words, zero padded.
+pub fn pack_name(name: &str) -> Option<[usize; 3]> {
+    let bytes = name.as_bytes();
+    if bytes.is_empty() || bytes.len() > NAME_LEN || bytes.contains(&0) {
+        return None;
+    }
+    let mut padded = [0u8; NAME_LEN];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    let mut words = [0; 3];
+    for (word, chunk) in words.iter_mut().zip(padded.chunks(8)) {
+        *word = usize::from_le_bytes(chunk.try_into().unwrap());
+    }
+    Some(words)
+}
+
+fn request(op: usize, name: &str, cap: &mut usize) -> isize {
+    let Some([a, b, c]) = pack_name(name) else {
+        return NS_INVALID;
+    };
+    let mut msg: IpcMessage = [op, a, b, c];
+    let ret = call_cap(CAP_NAME_SERVER, &mut msg, cap);
+    if ret < 0 { ret } else { msg[0] as isize }
+}
+
+/// Registers the endpoint in slot `endpoint` under `name`. Clients that look it up only get
+/// to send to it.
+pub fn register_service(name: &str, endpoint: usize) -> isize {
+    request(NS_REGISTER, name, &mut endpoint.clone())
+}
+
+/// Returns the slot of a copy of the endpoint registered under `name`.
+pub fn lookup_service(name: &str) -> isize {
+    let mut cap = NO_CAP;
+    let ret = request(NS_LOOKUP, name, &mut cap);
+    if ret < 0 {
+        return ret;
+    }
+    if cap == NO_CAP { NS_INVALID } else { cap as isize }
+}
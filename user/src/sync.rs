@@ -0,0 +1,111 @@
+//! Blocking synchronisation between threads. `Mutex` is backed by a kernel object named by a
+//! per-process id, `FutexMutex` only enters the kernel when the lock is contended.
+
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+use crate::{futex_wait, futex_wake, mutex_blocking_create, mutex_lock, mutex_unlock};
+
+/// Protects a `T` with a blocking kernel mutex.
+pub struct Mutex<T> {
+    id: usize,
+    data: UnsafeCell<T>
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub fn new(value: T) -> Self {
+        let id = mutex_blocking_create();
+        assert!(id >= 0);
+        Self { id: id as usize, data: UnsafeCell::new(value) }
+    }
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        mutex_lock(self.id);
+        MutexGuard { mutex: self }
+    }
+    /// Id of the kernel mutex, e.g. for `condvar_wait`.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+/// Holds the lock until dropped.
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        mutex_unlock(self.mutex.id);
+    }
+}
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// Locked, and somebody may be sleeping in `futex_wait`.
+const CONTENDED: u32 = 2;
+
+/// Protects a `T` with a lock word that is taken and released with atomics alone as long as
+/// nobody has to wait. Placed in shared memory, it also works across processes.
+pub struct FutexMutex<T> {
+    state: AtomicU32,
+    data: UnsafeCell<T>
+}
+
+unsafe impl<T: Send> Sync for FutexMutex<T> {}
+
+impl<T> FutexMutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self { state: AtomicU32::new(UNLOCKED), data: UnsafeCell::new(value) }
+    }
+    pub fn lock(&self) -> FutexMutexGuard<'_, T> {
+        if self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            // Whoever takes the lock from here on cannot know whether others still wait, so
+            // it marks the lock contended to make the unlock wake them.
+            while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+                futex_wait(&self.state, CONTENDED, None);
+            }
+        }
+        FutexMutexGuard { mutex: self }
+    }
+}
+
+/// Holds the lock until dropped.
+pub struct FutexMutexGuard<'a, T> {
+    mutex: &'a FutexMutex<T>
+}
+
+impl<T> Deref for FutexMutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for FutexMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for FutexMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.mutex.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            futex_wake(&self.mutex.state, 1);
+        }
+    }
+}
@@ -1,15 +1,79 @@
 use core::arch::asm;
+use core::sync::atomic::AtomicU32;
+use crate::{IpcMessage, Rusage, SchedStat, SignalAction, TimeSpec, Tms};
 
+const SYSCALL_IOCTL: usize = 29;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_SCHED_SETAFFINITY: usize = 122;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_GETRUSAGE: usize = 165;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
 const SYSCALL_SHUTDOWN: usize = 201;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_GETTID: usize = 1001;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_NICE: usize = 1040;
+const SYSCALL_ENDPOINT_CREATE: usize = 1100;
+const SYSCALL_SEND: usize = 1101;
+const SYSCALL_RECV: usize = 1102;
+const SYSCALL_CALL: usize = 1103;
+const SYSCALL_REPLY: usize = 1104;
+const SYSCALL_CAP_COPY: usize = 1200;
+const SYSCALL_CAP_MINT: usize = 1201;
+const SYSCALL_CAP_MOVE: usize = 1202;
+const SYSCALL_CAP_DELETE: usize = 1203;
+const SYSCALL_CAP_REVOKE: usize = 1204;
+const SYSCALL_CAP_IDENTIFY: usize = 1205;
+const SYSCALL_FRAME_ALLOC: usize = 1210;
+const SYSCALL_FRAME_MAP: usize = 1211;
+const SYSCALL_NOTIFICATION_CREATE: usize = 1300;
+const SYSCALL_SIGNAL: usize = 1301;
+const SYSCALL_WAIT: usize = 1302;
+const SYSCALL_POLL: usize = 1303;
+const SYSCALL_NOTIFICATION_BIND: usize = 1304;
+const SYSCALL_IRQ_HANDLER_GET: usize = 1400;
+const SYSCALL_IRQ_HANDLER_SET_NOTIFICATION: usize = 1401;
+const SYSCALL_IRQ_ACK: usize = 1402;
+const SYSCALL_MMIO_MAP: usize = 1410;
+const SYSCALL_SHM_CREATE: usize = 1500;
+const SYSCALL_SHM_MAP: usize = 1501;
+const SYSCALL_GET_IDLE_TIME: usize = 2000;
+const SYSCALL_GET_SCHED_STAT: usize = 2001;
+
+const CLOCK_MONOTONIC: usize = 1;
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TIOCSPGRP: usize = 0x5410;
 
 fn syscall(id: usize, args: [usize; 3]) -> isize {
     let mut ret: isize;
@@ -25,6 +89,140 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+fn syscall4(id: usize, args: [usize; 4]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+        "ecall",
+        inlateout("x10") args[0] => ret,
+        in("x11") args[1],
+        in("x12") args[2],
+        in("x13") args[3],
+        in("x17") id
+        );
+    }
+    ret
+}
+
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, request, arg])
+}
+
+/// IPC syscalls pass the message in a1 to a4 and the slot of a capability to transfer in a5,
+/// and may hand a new message and capability back in the same registers.
+fn ipc_syscall(id: usize, handle: usize, msg: &mut IpcMessage, cap: &mut usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+        "ecall",
+        inlateout("x10") handle => ret,
+        inlateout("x11") msg[0],
+        inlateout("x12") msg[1],
+        inlateout("x13") msg[2],
+        inlateout("x14") msg[3],
+        inlateout("x15") *cap,
+        in("x17") id
+        );
+    }
+    ret
+}
+
+pub fn sys_endpoint_create() -> isize {
+    syscall(SYSCALL_ENDPOINT_CREATE, [0, 0, 0])
+}
+
+pub fn sys_send(handle: usize, msg: &IpcMessage, cap: usize) -> isize {
+    ipc_syscall(SYSCALL_SEND, handle, &mut msg.clone(), &mut cap.clone())
+}
+
+pub fn sys_recv(handle: usize, msg: &mut IpcMessage, cap: &mut usize) -> isize {
+    ipc_syscall(SYSCALL_RECV, handle, msg, cap)
+}
+
+pub fn sys_call(handle: usize, msg: &mut IpcMessage, cap: &mut usize) -> isize {
+    ipc_syscall(SYSCALL_CALL, handle, msg, cap)
+}
+
+pub fn sys_reply(msg: &IpcMessage, cap: usize) -> isize {
+    ipc_syscall(SYSCALL_REPLY, 0, &mut msg.clone(), &mut cap.clone())
+}
+
+pub fn sys_notification_create() -> isize {
+    syscall(SYSCALL_NOTIFICATION_CREATE, [0, 0, 0])
+}
+
+pub fn sys_signal(slot: usize, bits: usize) -> isize {
+    syscall(SYSCALL_SIGNAL, [slot, bits, 0])
+}
+
+pub fn sys_wait(slot: usize) -> isize {
+    syscall(SYSCALL_WAIT, [slot, 0, 0])
+}
+
+pub fn sys_poll(slot: usize) -> isize {
+    syscall(SYSCALL_POLL, [slot, 0, 0])
+}
+
+pub fn sys_notification_bind(endpoint: usize, notification: usize) -> isize {
+    syscall(SYSCALL_NOTIFICATION_BIND, [endpoint, notification, 0])
+}
+
+pub fn sys_cap_copy(src: usize, dest: usize) -> isize {
+    syscall(SYSCALL_CAP_COPY, [src, dest, 0])
+}
+
+pub fn sys_cap_mint(src: usize, dest: usize, rights: usize, badge: usize) -> isize {
+    syscall4(SYSCALL_CAP_MINT, [src, dest, rights, badge])
+}
+
+pub fn sys_cap_move(src: usize, dest: usize) -> isize {
+    syscall(SYSCALL_CAP_MOVE, [src, dest, 0])
+}
+
+pub fn sys_cap_delete(slot: usize) -> isize {
+    syscall(SYSCALL_CAP_DELETE, [slot, 0, 0])
+}
+
+pub fn sys_cap_revoke(slot: usize) -> isize {
+    syscall(SYSCALL_CAP_REVOKE, [slot, 0, 0])
+}
+
+pub fn sys_cap_identify(slot: usize) -> isize {
+    syscall(SYSCALL_CAP_IDENTIFY, [slot, 0, 0])
+}
+
+pub fn sys_frame_alloc() -> isize {
+    syscall(SYSCALL_FRAME_ALLOC, [0, 0, 0])
+}
+
+pub fn sys_frame_map(aspace: usize, frame: usize, addr: usize, prot: usize) -> isize {
+    syscall4(SYSCALL_FRAME_MAP, [aspace, frame, addr, prot])
+}
+
+pub fn sys_shm_create(len: usize) -> isize {
+    syscall(SYSCALL_SHM_CREATE, [len, 0, 0])
+}
+
+pub fn sys_shm_map(aspace: usize, shm: usize, addr: usize, prot: usize) -> isize {
+    syscall4(SYSCALL_SHM_MAP, [aspace, shm, addr, prot])
+}
+
+pub fn sys_irq_handler_get(control: usize, irq: usize) -> isize {
+    syscall(SYSCALL_IRQ_HANDLER_GET, [control, irq, 0])
+}
+
+pub fn sys_irq_handler_set_notification(handler: usize, notification: usize) -> isize {
+    syscall(SYSCALL_IRQ_HANDLER_SET_NOTIFICATION, [handler, notification, 0])
+}
+
+pub fn sys_irq_ack(handler: usize) -> isize {
+    syscall(SYSCALL_IRQ_ACK, [handler, 0, 0])
+}
+
+pub fn sys_mmio_map(control: usize, aspace: usize, base: usize) -> isize {
+    syscall(SYSCALL_MMIO_MAP, [control, aspace, base])
+}
+
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
     syscall(
         SYSCALL_READ,
@@ -61,10 +259,129 @@ pub fn sys_exec(path: &str) -> isize {
     syscall(SYSCALL_EXEC, [path.as_ptr() as usize, 0, 0])
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: usize) -> isize {
+    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, options])
+}
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0])
+}
+
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0])
+}
+
+pub fn sys_waittid(tid: usize) -> isize {
+    syscall(SYSCALL_WAITTID, [tid, 0, 0])
+}
+
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    syscall(SYSCALL_MUTEX_CREATE, [blocking as usize, 0, 0])
+}
+
+pub fn sys_mutex_lock(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_LOCK, [id, 0, 0])
+}
+
+pub fn sys_mutex_unlock(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_UNLOCK, [id, 0, 0])
+}
+
+pub fn sys_semaphore_create(count: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_CREATE, [count, 0, 0])
+}
+
+pub fn sys_semaphore_up(id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_UP, [id, 0, 0])
+}
+
+pub fn sys_semaphore_down(id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_DOWN, [id, 0, 0])
+}
+
+pub fn sys_condvar_create() -> isize {
+    syscall(SYSCALL_CONDVAR_CREATE, [0, 0, 0])
+}
+
+pub fn sys_condvar_signal(id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_SIGNAL, [id, 0, 0])
+}
+
+pub fn sys_condvar_wait(id: usize, mutex_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_WAIT, [id, mutex_id, 0])
+}
+
+pub fn sys_futex(word: &AtomicU32, op: usize, val: usize, timeout: *const TimeSpec) -> isize {
+    syscall4(SYSCALL_FUTEX, [word.as_ptr() as usize, op, val, timeout as usize])
+}
+
+pub fn sys_kill(pid: usize, signum: usize) -> isize {
+    syscall(SYSCALL_KILL, [pid, signum, 0])
+}
+
+pub fn sys_sigaction(signum: usize, action: *const SignalAction, old_action: *mut SignalAction) -> isize {
+    syscall(SYSCALL_SIGACTION, [signum, action as usize, old_action as usize])
+}
+
+pub fn sys_sigprocmask(how: usize, set: u32) -> isize {
+    syscall(SYSCALL_SIGPROCMASK, [how, set as usize, 0])
+}
+
+pub fn sys_sigreturn() -> isize {
+    syscall(SYSCALL_SIGRETURN, [0, 0, 0])
+}
+
+pub fn sys_set_priority(prio: isize) -> isize {
+    syscall(SYSCALL_SET_PRIORITY, [prio as usize, 0, 0])
+}
+
+pub fn sys_nice(inc: isize) -> isize {
+    syscall(SYSCALL_NICE, [inc as usize, 0, 0])
+}
+
+pub fn sys_times(tms: &mut Tms) -> isize {
+    syscall(SYSCALL_TIMES, [tms as *mut _ as usize, 0, 0])
+}
+
+pub fn sys_getrusage(who: isize, usage: &mut Rusage) -> isize {
+    syscall(SYSCALL_GETRUSAGE, [who as usize, usage as *mut _ as usize, 0])
 }
 
 pub fn sys_shutdown(code: i32) -> isize {
     syscall(SYSCALL_SHUTDOWN, [code as usize, 0, 0])
-}
\ No newline at end of file
+}
+pub fn sys_nanosleep(req: &TimeSpec) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [req as *const _ as usize, 0, 0])
+}
+
+pub fn sys_clock_gettime(tp: &mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [CLOCK_MONOTONIC, tp as *mut _ as usize, 0])
+}
+
+pub fn sys_get_idle_time(hart: usize) -> isize {
+    syscall(SYSCALL_GET_IDLE_TIME, [hart, 0, 0])
+}
+
+pub fn sys_get_sched_stat(hart: usize, stat: &mut SchedStat) -> isize {
+    syscall(SYSCALL_GET_SCHED_STAT, [hart, stat as *mut _ as usize, 0])
+}
+
+pub fn sys_sched_setaffinity(pid: usize, mask: &usize) -> isize {
+    syscall(SYSCALL_SCHED_SETAFFINITY, [pid, size_of::<usize>(), mask as *const _ as usize])
+}
+
+pub fn sys_brk(addr: usize) -> isize {
+    syscall(SYSCALL_BRK, [addr, 0, 0])
+}
+
+pub fn sys_mmap(addr: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MMAP, [addr, len, prot])
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [addr, len, 0])
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MPROTECT, [addr, len, prot])
+}